@@ -0,0 +1,191 @@
+use std::{
+    io,
+    os::fd::AsRawFd,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+use tokio::io::{
+    Interest, Ready,
+    unix::{AsyncFd, AsyncFdReadyGuard},
+};
+
+use crate::{PgConn, PgError, PgNotify, PgResult, PgSocket};
+
+/// A connection driven by a tokio runtime: queries are awaited on the readiness of the
+/// connection socket instead of blocking the thread.
+///
+/// The connection is switched to non-blocking mode. Its socket is registered once, so the
+/// connection must not be reset while wrapped.
+pub struct AsyncPgConn {
+    // Declared first to be deregistered before the connection closes the socket.
+    socket: AsyncFd<PgSocket>,
+    conn: PgConn,
+}
+
+impl AsyncPgConn {
+    /// Wrap an open connection; must be called within a tokio runtime.
+    pub fn new(mut conn: PgConn) -> Result<AsyncPgConn, PgError> {
+        conn.set_nonblocking(true)?;
+        let socket =
+            AsyncFd::with_interest(conn.socket(), Interest::READABLE | Interest::WRITABLE)?;
+        Ok(AsyncPgConn { socket, conn })
+    }
+
+    /// The wrapped connection, e.g. to inspect its status or use the accessors that don't
+    /// wait on the server.
+    pub fn conn(&self) -> &PgConn {
+        &self.conn
+    }
+
+    /// Give back the wrapped connection, still in non-blocking mode.
+    pub fn into_inner(self) -> PgConn {
+        self.conn
+    }
+
+    /// Execute a query, like [`PgConn::exec`], returning the last of its results.
+    ///
+    /// If the query contains several statements, only the last result is returned, as
+    /// `PQexec` does; a statement starting a COPY ends the query with its COPY result, after
+    /// which the caller must transfer the data.
+    ///
+    /// The future is cancel-safe: if it is dropped before completing, the results of its
+    /// query are discarded by the next call.
+    pub async fn exec(&mut self, sql: &str) -> Result<PgResult, PgError> {
+        self.discard_results().await?;
+        self.conn.send_query(sql)?;
+        self.flush().await?;
+
+        let mut last = None;
+        while let Some(res) = self.next_result().await? {
+            // libpq keeps returning the COPY result while the copy is in progress.
+            let copy = res.is_copy();
+            last = Some(res);
+            if copy {
+                break;
+            }
+        }
+        last.ok_or_else(|| PgError::Connection(self.conn.error_message()))
+    }
+
+    /// The notifications received on the channels this connection listens on, the async
+    /// counterpart of [`PgConn::listen_loop`].
+    ///
+    /// The socket is only read while the stream is polled. The stream ends after yielding
+    /// the error that reading from the connection failed with, e.g. when it was lost.
+    pub fn notifications(&mut self) -> impl Stream<Item = Result<PgNotify, PgError>> + '_ {
+        Notifications {
+            conn: self,
+            done: false,
+        }
+    }
+
+    /// Send all the queued data, waiting for the socket to be write-ready and consuming
+    /// input meanwhile, as [`PgConn::flush`] requires.
+    async fn flush(&mut self) -> Result<(), PgError> {
+        while !self.conn.flush()? {
+            let mut guard = self
+                .socket
+                .ready(Interest::READABLE | Interest::WRITABLE)
+                .await?;
+            let ready = guard.ready();
+            if ready.is_readable() {
+                self.conn.consume_input().map_err(PgError::Connection)?;
+                clear_read_ready_if_drained(&mut guard);
+            }
+            if ready.is_writable() {
+                // The flush that follows tells whether the socket is still write-ready.
+                guard.clear_ready_matching(Ready::WRITABLE);
+            }
+        }
+        Ok(())
+    }
+
+    /// Send the rest of a query left behind by a cancelled [`AsyncPgConn::exec`] and read
+    /// its results, so that a new query can be sent.
+    async fn discard_results(&mut self) -> Result<(), PgError> {
+        self.flush().await?;
+        while let Some(res) = self.next_result().await? {
+            if res.is_copy() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait until the connection is no longer busy, then return its next result.
+    async fn next_result(&mut self) -> Result<Option<PgResult>, PgError> {
+        self.wait_input(|conn| !conn.is_busy()).await?;
+        Ok(self.conn.get_result())
+    }
+
+    /// Read from the socket whenever it becomes readable until `done` holds.
+    async fn wait_input<F>(&mut self, mut done: F) -> Result<(), PgError>
+    where
+        F: FnMut(&mut PgConn) -> bool,
+    {
+        while !done(&mut self.conn) {
+            let mut guard = self.socket.readable().await?;
+            self.conn.consume_input().map_err(PgError::Connection)?;
+            clear_read_ready_if_drained(&mut guard);
+        }
+        Ok(())
+    }
+}
+
+struct Notifications<'a> {
+    conn: &'a mut AsyncPgConn,
+    done: bool,
+}
+
+impl Stream for Notifications<'_> {
+    type Item = Result<PgNotify, PgError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            if let Some(notify) = this.conn.conn.notifies() {
+                return Poll::Ready(Some(Ok(notify)));
+            }
+
+            let mut guard = match this.conn.socket.poll_read_ready(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Ok(guard)) => guard,
+                Poll::Ready(Err(e)) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(e.into())));
+                }
+            };
+            if let Err(message) = this.conn.conn.consume_input() {
+                this.done = true;
+                return Poll::Ready(Some(Err(PgError::Connection(message))));
+            }
+            clear_read_ready_if_drained(&mut guard);
+        }
+    }
+}
+
+/// Clear the read readiness of the socket once no data is left to read on it.
+///
+/// A single [`PgConn::consume_input`] may leave data behind, since libpq reads at most a
+/// buffer at a time, and a single record over TLS: clearing the readiness regardless would
+/// wait for more data to arrive before reading the data already there.
+fn clear_read_ready_if_drained(guard: &mut AsyncFdReadyGuard<'_, PgSocket>) {
+    let mut byte = 0u8;
+    let peeked = unsafe {
+        libc::recv(
+            guard.get_ref().as_raw_fd(),
+            &mut byte as *mut u8 as *mut libc::c_void,
+            1,
+            libc::MSG_PEEK | libc::MSG_DONTWAIT,
+        )
+    };
+    if peeked == -1 && io::Error::last_os_error().kind() == io::ErrorKind::WouldBlock {
+        guard.clear_ready_matching(Ready::READABLE);
+    }
+}
@@ -0,0 +1,370 @@
+use std::io::{Error, ErrorKind};
+
+use crate::{
+    ExecStatusType_PGRES_COPY_IN, ExecStatusType_PGRES_COPY_OUT, PgConn, PgError,
+    copy::{COPY_CHUNK_SIZE, expect_copy},
+};
+
+// The signature, flags field and header extension length opening a binary COPY stream.
+const HEADER: &[u8; 19] = b"PGCOPY\n\xff\r\n\0\0\0\0\0\0\0\0\0";
+
+/// Types that can be written as a field of a binary COPY, see [`BinaryCopyWriter`].
+///
+/// The encoding must match the type of the target column exactly, e.g. an `i32` for an
+/// `int4` column: unlike the text format, binary values are not converted by the server.
+pub trait ToBinary {
+    /// The value in Postgres binary format, `None` being SQL NULL.
+    fn to_binary(&self) -> Option<Vec<u8>>;
+}
+
+/// Types that can be decoded from a field of a binary COPY, see [`BinaryRow::get`].
+pub trait FromBinary: Sized {
+    /// Decode a non-NULL value, `None` if it is not valid.
+    fn from_binary(value: &[u8]) -> Option<Self>;
+
+    /// The value to decode SQL NULL into, by default none.
+    fn from_binary_null() -> Option<Self> {
+        None
+    }
+}
+
+macro_rules! impl_binary_numeric {
+    ($($t:ty),* $(,)?) => {
+        $(
+            impl ToBinary for $t {
+                fn to_binary(&self) -> Option<Vec<u8>> {
+                    Some(self.to_be_bytes().to_vec())
+                }
+            }
+
+            impl FromBinary for $t {
+                fn from_binary(value: &[u8]) -> Option<Self> {
+                    value.try_into().ok().map(<$t>::from_be_bytes)
+                }
+            }
+        )*
+    };
+}
+
+impl_binary_numeric!(i16, i32, i64, f32, f64);
+
+impl ToBinary for bool {
+    fn to_binary(&self) -> Option<Vec<u8>> {
+        Some(vec![*self as u8])
+    }
+}
+
+impl FromBinary for bool {
+    fn from_binary(value: &[u8]) -> Option<Self> {
+        match value {
+            [0] => Some(false),
+            [1] => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl ToBinary for &str {
+    fn to_binary(&self) -> Option<Vec<u8>> {
+        Some(self.as_bytes().to_vec())
+    }
+}
+
+impl ToBinary for String {
+    fn to_binary(&self) -> Option<Vec<u8>> {
+        Some(self.as_bytes().to_vec())
+    }
+}
+
+impl FromBinary for String {
+    fn from_binary(value: &[u8]) -> Option<Self> {
+        String::from_utf8(value.to_vec()).ok()
+    }
+}
+
+/// A `bytea` value.
+impl ToBinary for &[u8] {
+    fn to_binary(&self) -> Option<Vec<u8>> {
+        Some(self.to_vec())
+    }
+}
+
+/// A `bytea` value.
+impl ToBinary for Vec<u8> {
+    fn to_binary(&self) -> Option<Vec<u8>> {
+        Some(self.clone())
+    }
+}
+
+impl FromBinary for Vec<u8> {
+    fn from_binary(value: &[u8]) -> Option<Self> {
+        Some(value.to_vec())
+    }
+}
+
+impl<T: ToBinary> ToBinary for Option<T> {
+    fn to_binary(&self) -> Option<Vec<u8>> {
+        self.as_ref().and_then(ToBinary::to_binary)
+    }
+}
+
+impl<T: FromBinary> FromBinary for Option<T> {
+    fn from_binary(value: &[u8]) -> Option<Self> {
+        T::from_binary(value).map(Some)
+    }
+
+    fn from_binary_null() -> Option<Self> {
+        Some(None)
+    }
+}
+
+/// Rows being loaded with a binary `COPY ... FROM STDIN`, started by
+/// [`PgConn::copy_in_binary`].
+///
+/// Dropping the writer without calling [`BinaryCopyWriter::finish`] aborts the copy.
+pub struct BinaryCopyWriter<'a> {
+    conn: &'a mut PgConn,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+/// The rows of a binary `COPY ... TO STDOUT`, started by [`PgConn::copy_out_binary`].
+///
+/// Dropping the reader before the last row reads and discards the rest of the copy.
+pub struct BinaryCopyReader<'a> {
+    conn: &'a mut PgConn,
+    buf: Vec<u8>,
+    pos: usize,
+    header_read: bool,
+    rows: i32,
+    done: bool,
+}
+
+/// A row read by a [`BinaryCopyReader`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinaryRow {
+    row: i32,
+    values: Vec<Option<Vec<u8>>>,
+}
+
+impl PgConn {
+    /// Start loading rows in binary format with `COPY table_and_columns FROM STDIN`.
+    ///
+    /// `table_and_columns`, e.g. `items (id, name)`, is inserted in the command as is, so it
+    /// must be escaped by the caller.
+    pub fn copy_in_binary(
+        &mut self,
+        table_and_columns: &str,
+    ) -> Result<BinaryCopyWriter<'_>, PgError> {
+        let query = format!(
+            "COPY {} FROM STDIN WITH (FORMAT binary);",
+            table_and_columns
+        );
+        let res = self.exec(&query)?;
+        expect_copy(res, ExecStatusType_PGRES_COPY_IN)?;
+        Ok(BinaryCopyWriter {
+            conn: self,
+            buf: HEADER.to_vec(),
+            done: false,
+        })
+    }
+
+    /// Start reading the rows of a query in binary format with `COPY (sql) TO STDOUT`.
+    pub fn copy_out_binary(&mut self, sql: &str) -> Result<BinaryCopyReader<'_>, PgError> {
+        let sql = sql.trim_end().trim_end_matches(';');
+        let res = self.exec(&format!("COPY ({}) TO STDOUT WITH (FORMAT binary);", sql))?;
+        expect_copy(res, ExecStatusType_PGRES_COPY_OUT)?;
+        Ok(BinaryCopyReader {
+            conn: self,
+            buf: Vec::new(),
+            pos: 0,
+            header_read: false,
+            rows: 0,
+            done: false,
+        })
+    }
+}
+
+impl BinaryCopyWriter<'_> {
+    /// Write a row with one value per column of the copy.
+    ///
+    /// A row with more than `i16::MAX` fields or a field larger than 2GB is rejected as
+    /// [`ErrorKind::InvalidInput`], leaving the copy as it was.
+    pub fn write_row(&mut self, values: &[&dyn ToBinary]) -> Result<(), PgError> {
+        let count = i16::try_from(values.len())
+            .map_err(|_| invalid_input("too many fields in a COPY row"))?;
+        let fields = values
+            .iter()
+            .map(|value| value.to_binary())
+            .collect::<Vec<_>>();
+        if fields
+            .iter()
+            .flatten()
+            .any(|bytes| i32::try_from(bytes.len()).is_err())
+        {
+            return Err(invalid_input("COPY field larger than 2GB"));
+        }
+
+        self.buf.extend_from_slice(&count.to_be_bytes());
+        for field in fields {
+            match field {
+                Some(bytes) => {
+                    self.buf
+                        .extend_from_slice(&(bytes.len() as i32).to_be_bytes());
+                    self.buf.extend_from_slice(&bytes);
+                }
+                None => self.buf.extend_from_slice(&(-1i32).to_be_bytes()),
+            }
+        }
+
+        if self.buf.len() >= COPY_CHUNK_SIZE {
+            self.conn.send_copy_data(&self.buf)?;
+            self.buf.clear();
+        }
+        Ok(())
+    }
+
+    /// Write the trailer and end the copy, returning the number of rows loaded.
+    pub fn finish(mut self) -> Result<u64, PgError> {
+        self.buf.extend_from_slice(&(-1i16).to_be_bytes());
+        self.conn.send_copy_data(&self.buf)?;
+        self.conn.send_copy_end(None)?;
+        // Only now is there nothing left for the drop to abort.
+        self.done = true;
+        self.conn.finish_copy()
+    }
+}
+
+impl Drop for BinaryCopyWriter<'_> {
+    fn drop(&mut self) {
+        // Only drain once the end of the copy is queued: until then, libpq keeps returning
+        // the COPY result.
+        if !self.done && matches!(self.conn.put_copy_end(Some("COPY aborted")), Ok(true)) {
+            let _ = self.conn.finish_copy();
+        }
+    }
+}
+
+impl BinaryCopyReader<'_> {
+    /// The next row, or `None` once all of them were read and the copy succeeded.
+    pub fn next_row(&mut self) -> Result<Option<BinaryRow>, PgError> {
+        if self.done {
+            return Ok(None);
+        }
+        if !self.header_read {
+            let header: [u8; 19] = self.take(HEADER.len())?.try_into().unwrap();
+            if header[..11] != HEADER[..11] {
+                return Err(invalid_data("missing binary COPY signature"));
+            }
+            let extension = u32::from_be_bytes(header[15..].try_into().unwrap());
+            self.take(extension as usize)?;
+            self.header_read = true;
+        }
+
+        let count = i16::from_be_bytes(self.take(2)?.try_into().unwrap());
+        if count < -1 {
+            return Err(invalid_data("negative binary COPY field count"));
+        }
+        if count == -1 {
+            self.done = true;
+            while self.conn.get_copy_data()?.is_some() {}
+            self.conn.finish_copy()?;
+            return Ok(None);
+        }
+
+        let mut values = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let len = i32::from_be_bytes(self.take(4)?.try_into().unwrap());
+            values.push(match len {
+                -1 => None,
+                len if len < -1 => return Err(invalid_data("negative binary COPY field length")),
+                len => Some(self.take(len as usize)?.to_vec()),
+            });
+        }
+        self.rows += 1;
+        Ok(Some(BinaryRow {
+            row: self.rows - 1,
+            values,
+        }))
+    }
+
+    /// The next `n` bytes of the stream, receiving data as needed.
+    fn take(&mut self, n: usize) -> Result<&[u8], PgError> {
+        while self.buf.len() - self.pos < n {
+            match self.conn.get_copy_data()? {
+                Some(data) => {
+                    self.buf.drain(..self.pos);
+                    self.pos = 0;
+                    self.buf.extend_from_slice(&data);
+                }
+                None => return Err(invalid_data("binary COPY data ended unexpectedly")),
+            }
+        }
+        self.pos += n;
+        Ok(&self.buf[self.pos - n..self.pos])
+    }
+}
+
+impl Iterator for BinaryCopyReader<'_> {
+    type Item = Result<BinaryRow, PgError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_row().transpose()
+    }
+}
+
+impl Drop for BinaryCopyReader<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            while let Ok(Some(_)) = self.conn.get_copy_data() {}
+            let _ = self.conn.finish_copy();
+        }
+    }
+}
+
+impl BinaryRow {
+    /// The number of fields in the row.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Whether the field at `col` is SQL NULL.
+    pub fn is_null(&self, col: usize) -> bool {
+        matches!(self.values.get(col), Some(None))
+    }
+
+    /// The raw bytes of the field at `col`, `None` if it is NULL or out of range.
+    pub fn raw(&self, col: usize) -> Option<&[u8]> {
+        self.values.get(col)?.as_deref()
+    }
+
+    /// Decode the field at `col`, failing if it is out of range, NULL where `T` is not an
+    /// `Option`, or not a valid encoding of `T`.
+    pub fn get<T: FromBinary>(&self, col: usize) -> Result<T, PgError> {
+        let value = self
+            .values
+            .get(col)
+            .ok_or_else(|| PgError::ColumnNotFound(col.to_string()))?;
+        let col = col as i32;
+        match value {
+            None => T::from_binary_null().ok_or(PgError::UnexpectedNull { row: self.row, col }),
+            Some(bytes) => T::from_binary(bytes).ok_or_else(|| PgError::InvalidValue {
+                row: self.row,
+                col,
+                value: format!("{:02x?}", bytes),
+            }),
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> PgError {
+    PgError::Io(Error::new(ErrorKind::InvalidData, message))
+}
+
+fn invalid_input(message: &str) -> PgError {
+    PgError::Io(Error::new(ErrorKind::InvalidInput, message))
+}
@@ -0,0 +1,260 @@
+use std::{
+    ffi::CString,
+    io::{Error, ErrorKind, Read, Write},
+};
+
+use crate::{
+    ExecStatusType, ExecStatusType_PGRES_COPY_IN, ExecStatusType_PGRES_COPY_OUT, PQgetCopyData,
+    PQputCopyData, PQputCopyEnd, PgConn, PgError, PgResult, PgSocketPollResult, PqMem,
+};
+
+// Rows are buffered and sent to the server in chunks of about this size.
+pub(crate) const COPY_CHUNK_SIZE: usize = 64 * 1024;
+
+impl PgConn {
+    /// Send data to the server during `COPY FROM STDIN`, returning `false` if, in
+    /// non-blocking mode, it could not be queued yet.
+    ///
+    /// libpq takes at most `i32::MAX` bytes at a time; larger data is rejected as
+    /// [`ErrorKind::InvalidInput`] before anything is sent.
+    /// See also [PQputCopyData](https://www.postgresql.org/docs/current/libpq-copy.html#LIBPQ-PQPUTCOPYDATA).
+    pub fn put_copy_data(&mut self, data: &[u8]) -> Result<bool, PgError> {
+        let len = i32::try_from(data.len())
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "COPY data larger than 2GB"))?;
+        match unsafe { PQputCopyData(self.conn, data.as_ptr() as *const _, len) } {
+            1 => Ok(true),
+            0 => Ok(false),
+            _ => Err(PgError::Connection(self.error_message())),
+        }
+    }
+
+    /// End `COPY FROM STDIN`, successfully or, if `error` is given, making the command fail
+    /// with that message. The outcome of the command is then available from
+    /// [`PgConn::get_result`].
+    /// See also [PQputCopyEnd](https://www.postgresql.org/docs/current/libpq-copy.html#LIBPQ-PQPUTCOPYEND).
+    pub fn put_copy_end(&mut self, error: Option<&str>) -> Result<bool, PgError> {
+        let error = error.map(CString::new).transpose()?;
+        let ptr = error.as_ref().map_or(std::ptr::null(), |e| e.as_ptr());
+        match unsafe { PQputCopyEnd(self.conn, ptr) } {
+            1 => Ok(true),
+            0 => Ok(false),
+            _ => Err(PgError::Connection(self.error_message())),
+        }
+    }
+
+    /// Load rows into `table` with `COPY ... FROM STDIN`, returning the number of rows loaded.
+    ///
+    /// Each row holds one value per column in `columns` (all of them if empty), in text
+    /// format, `None` being NULL. The table name (each part of it, if schema-qualified) and
+    /// the column names are escaped as identifiers, so they are case sensitive.
+    pub fn copy_in<I>(&mut self, table: &str, columns: &[&str], rows: I) -> Result<u64, PgError>
+    where
+        I: IntoIterator<Item = Vec<Option<String>>>,
+    {
+        let table = table
+            .split('.')
+            .map(|part| self.escape_identifier(part))
+            .collect::<Result<Vec<_>, _>>()?
+            .join(".");
+        let columns = columns
+            .iter()
+            .map(|column| self.escape_identifier(column))
+            .collect::<Result<Vec<_>, _>>()?;
+        let query = if columns.is_empty() {
+            format!("COPY {} FROM STDIN;", table)
+        } else {
+            format!("COPY {} ({}) FROM STDIN;", table, columns.join(", "))
+        };
+
+        let res = self.exec(&query)?;
+        expect_copy(res, ExecStatusType_PGRES_COPY_IN)?;
+
+        let mut buf = String::new();
+        for row in rows {
+            for (i, value) in row.iter().enumerate() {
+                if i > 0 {
+                    buf.push('\t');
+                }
+                match value {
+                    Some(value) => escape_copy_text(value, &mut buf),
+                    None => buf.push_str("\\N"),
+                }
+            }
+            buf.push('\n');
+
+            if buf.len() >= COPY_CHUNK_SIZE {
+                self.send_copy_data(buf.as_bytes())?;
+                buf.clear();
+            }
+        }
+        if !buf.is_empty() {
+            self.send_copy_data(buf.as_bytes())?;
+        }
+        self.send_copy_end(None)?;
+
+        self.finish_copy()
+    }
+
+    /// Load data already in COPY text format from `src` with `COPY table_and_columns FROM
+    /// STDIN`, returning the number of rows loaded.
+    ///
+    /// `table_and_columns`, e.g. `items (id, name)`, is inserted in the command as is, so it
+    /// must be escaped by the caller. The data is streamed in chunks, verbatim; if reading
+    /// from `src` fails, the copy is aborted and the read error returned.
+    pub fn copy_in_reader<R: Read>(
+        &mut self,
+        table_and_columns: &str,
+        src: &mut R,
+    ) -> Result<u64, PgError> {
+        let res = self.exec(&format!("COPY {} FROM STDIN;", table_and_columns))?;
+        expect_copy(res, ExecStatusType_PGRES_COPY_IN)?;
+
+        let mut buf = vec![0; COPY_CHUNK_SIZE];
+        loop {
+            match src.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    self.send_copy_data(&buf[..n])?;
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    // Make the command fail so the connection stays usable; its error is
+                    // the expected outcome.
+                    self.send_copy_end(Some("reading the COPY data failed"))?;
+                    let _ = self.finish_copy();
+                    return Err(e.into());
+                }
+            }
+        }
+        self.send_copy_end(None)?;
+
+        self.finish_copy()
+    }
+
+    /// Receive the next row of data during `COPY TO STDOUT`, blocking until it arrives, or
+    /// `None` once the copy is done; its outcome is then available from
+    /// [`PgConn::get_result`].
+    /// See also [PQgetCopyData](https://www.postgresql.org/docs/current/libpq-copy.html#LIBPQ-PQGETCOPYDATA).
+    pub fn get_copy_data(&mut self) -> Result<Option<Vec<u8>>, PgError> {
+        let mut buf = std::ptr::null_mut();
+        let len = unsafe { PQgetCopyData(self.conn, &mut buf, 0) };
+        match len {
+            -1 => Ok(None),
+            len if len < 0 => Err(PgError::Connection(self.error_message())),
+            len => unsafe {
+                let buf = PqMem::new(buf).expect("PQgetCopyData returned data without a buffer.");
+                Ok(Some(buf.as_bytes(len as usize).to_vec()))
+            },
+        }
+    }
+
+    /// Export the rows of a query as CSV with a header line, via `COPY (sql) TO STDOUT`,
+    /// returning the number of rows written.
+    ///
+    /// Data is written as it arrives, without building a result in memory.
+    pub fn copy_query_to_csv<W: Write>(&mut self, sql: &str, w: &mut W) -> Result<u64, PgError> {
+        let sql = sql.trim_end().trim_end_matches(';');
+        let res = self.exec(&format!("COPY ({}) TO STDOUT WITH CSV HEADER;", sql))?;
+        expect_copy(res, ExecStatusType_PGRES_COPY_OUT)?;
+
+        while let Some(data) = self.get_copy_data()? {
+            if let Err(e) = w.write_all(&data) {
+                // Drain the copy so the connection stays usable.
+                while let Ok(Some(_)) = self.get_copy_data() {}
+                while self.get_result().is_some() {}
+                return Err(e.into());
+            }
+        }
+
+        self.finish_copy()
+    }
+
+    /// Collect the results following the end of a COPY, returning the number of rows copied.
+    ///
+    /// Fails with [`PgError::CopyInProgress`] if the copy was not actually ended, since libpq
+    /// then keeps returning the COPY result.
+    pub(crate) fn finish_copy(&mut self) -> Result<u64, PgError> {
+        let mut outcome = None;
+        while let Some(res) = self.get_result() {
+            if res.is_copy() {
+                return Err(PgError::CopyInProgress(res.status()));
+            }
+            outcome = Some(res.check().map(|res| res.cmd_tuples()));
+        }
+        outcome.unwrap_or_else(|| Err(PgError::Connection("no COPY outcome received".to_string())))
+    }
+
+    /// Queue data with [`PgConn::put_copy_data`], waiting for the socket to be write-ready
+    /// while, in non-blocking mode, it cannot be queued yet.
+    pub(crate) fn send_copy_data(&mut self, data: &[u8]) -> Result<(), PgError> {
+        while !self.put_copy_data(data)? {
+            self.wait_writable()?;
+        }
+        Ok(())
+    }
+
+    /// Like [`PgConn::send_copy_data`], for [`PgConn::put_copy_end`].
+    pub(crate) fn send_copy_end(&mut self, error: Option<&str>) -> Result<(), PgError> {
+        while !self.put_copy_end(error)? {
+            self.wait_writable()?;
+        }
+        Ok(())
+    }
+
+    /// Wait for the socket to be write-ready, consuming input meanwhile, then try to send
+    /// the queued data, as [`PgConn::flush`] requires.
+    fn wait_writable(&mut self) -> Result<(), PgError> {
+        match self.socket().poll_duration(true, true, None) {
+            Ok(()) => {
+                self.consume_input().map_err(PgError::Connection)?;
+                self.flush()?;
+                Ok(())
+            }
+            Err(PgSocketPollResult::Timeout) => Err(PgError::Timeout),
+            Err(PgSocketPollResult::Error(message)) => Err(PgError::Connection(message)),
+        }
+    }
+}
+
+/// Check that a COPY command entered the `expected` COPY state.
+pub(crate) fn expect_copy(res: PgResult, expected: ExecStatusType) -> Result<(), PgError> {
+    match res.check() {
+        Err(PgError::CopyInProgress(status)) if status == expected => Ok(()),
+        Err(PgError::CopyInProgress(status)) => Err(PgError::Query {
+            status,
+            message: "COPY started in the wrong direction".to_string(),
+            sqlstate: None,
+        }),
+        Err(e) => Err(e),
+        Ok(res) => Err(PgError::Query {
+            status: res.status(),
+            message: "COPY did not start".to_string(),
+            sqlstate: None,
+        }),
+    }
+}
+
+/// A field in COPY text format, as written by [`PgConn::copy_in`]: `\N` for NULL, otherwise
+/// the value with backslashes, tabs, newlines and carriage returns escaped.
+pub fn copy_escape_field(value: Option<&str>) -> String {
+    let mut buf = String::new();
+    match value {
+        Some(value) => escape_copy_text(value, &mut buf),
+        None => buf.push_str("\\N"),
+    }
+    buf
+}
+
+/// Append `value` in COPY text format, escaping backslashes and the characters that would
+/// otherwise delimit fields and rows.
+fn escape_copy_text(value: &str, buf: &mut String) {
+    for c in value.chars() {
+        match c {
+            '\\' => buf.push_str("\\\\"),
+            '\t' => buf.push_str("\\t"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            c => buf.push(c),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::os::raw::{c_char, c_int, c_uint, c_void};
+
+use crate::{PGconn, PGresult, PgConn, PgError, PgResult};
+
+type PGEventProc = Option<unsafe extern "C" fn(c_uint, *mut c_void, *mut c_void) -> c_int>;
+
+// From libpq-events.h, which the generated bindings don't cover.
+unsafe extern "C" {
+    fn PQregisterEventProc(
+        conn: *mut PGconn,
+        proc: PGEventProc,
+        name: *const c_char,
+        pass_through: *mut c_void,
+    ) -> c_int;
+    fn PQsetInstanceData(conn: *mut PGconn, proc: PGEventProc, data: *mut c_void) -> c_int;
+    fn PQinstanceData(conn: *const PGconn, proc: PGEventProc) -> *mut c_void;
+    fn PQresultSetInstanceData(res: *mut PGresult, proc: PGEventProc, data: *mut c_void) -> c_int;
+    fn PQresultInstanceData(res: *const PGresult, proc: PGEventProc) -> *mut c_void;
+}
+
+/// The event procedure the instance data of results is attached to, accepting every event:
+/// the data is a plain integer, so there is nothing to copy or free.
+unsafe extern "C" fn instance_data_event(
+    _id: c_uint,
+    _info: *mut c_void,
+    _pass_through: *mut c_void,
+) -> c_int {
+    1
+}
+
+impl PgConn {
+    /// Let the results created by the connection from now on hold instance data, see
+    /// [`PgResult::set_instance_data`]. Calling it again has no effect.
+    /// See also [PQregisterEventProc](https://www.postgresql.org/docs/current/libpq-events.html#LIBPQ-PQREGISTEREVENTPROC).
+    pub fn enable_instance_data(&mut self) -> Result<(), PgError> {
+        unsafe {
+            // The connection's own instance data marks the procedure as registered.
+            if !PQinstanceData(self.conn, Some(instance_data_event)).is_null() {
+                return Ok(());
+            }
+            let registered = PQregisterEventProc(
+                self.conn,
+                Some(instance_data_event),
+                c"libpq-rs instance data".as_ptr(),
+                std::ptr::null_mut(),
+            ) == 1
+                && PQsetInstanceData(
+                    self.conn,
+                    Some(instance_data_event),
+                    std::ptr::dangling_mut(),
+                ) == 1;
+            if registered {
+                Ok(())
+            } else {
+                Err(PgError::Connection(
+                    "failed to register the instance data event procedure".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+impl PgResult {
+    /// Attach a value to the result, e.g. to correlate it with the query it answers in
+    /// pipeline mode, replacing any previous one; `0` removes it.
+    ///
+    /// Returns `false` if the result cannot hold one because it was created before
+    /// [`PgConn::enable_instance_data`] or not by a connection, as with
+    /// [`PgResult::make_empty`] or a clone.
+    /// See also [PQresultSetInstanceData](https://www.postgresql.org/docs/current/libpq-events.html#LIBPQ-PQRESULTSETINSTANCEDATA).
+    pub fn set_instance_data(&mut self, data: usize) -> bool {
+        unsafe {
+            PQresultSetInstanceData(
+                self.res,
+                Some(instance_data_event),
+                std::ptr::without_provenance_mut(data),
+            ) == 1
+        }
+    }
+
+    /// The value attached with [`PgResult::set_instance_data`], if any.
+    /// See also [PQresultInstanceData](https://www.postgresql.org/docs/current/libpq-events.html#LIBPQ-PQRESULTINSTANCEDATA).
+    pub fn instance_data(&self) -> Option<usize> {
+        let data = unsafe { PQresultInstanceData(self.res, Some(instance_data_event)) };
+        (!data.is_null()).then(|| data.addr())
+    }
+}
@@ -1,22 +1,149 @@
+//! Safe wrappers around libpq, the PostgreSQL client library.
+//!
+//! The wrappers need no Cargo feature; integrations with other crates are opt-in:
+//!
+//! - `chrono`: [`FromSql`] for `chrono` dates and timestamps;
+//! - `json`: [`FromSql`] for `serde_json::Value` and `PgResult::get_json`;
+//! - `log`: `PgConn::log_notices`, forwarding server notices to the `log` crate;
+//! - `rust_decimal`: [`FromSql`] and [`ToSql`] for `rust_decimal::Decimal`;
+//! - `uuid`: [`FromSql`] and [`ToSql`] for `uuid::Uuid`;
+//! - `pool`: a blocking connection pool, `PgPool`;
+//! - `tokio`: a connection driven by a tokio runtime, `AsyncPgConn`.
+
 use std::{
+    collections::{HashMap, HashSet},
     ffi::{CString, NulError},
     fmt::Display,
-    io::{Read, Seek},
+    io::Write,
     ops::ControlFlow,
     os::raw::{c_char, c_void},
     ptr::null_mut,
+    str::Utf8Error,
+    sync::atomic::{AtomicBool, Ordering},
 };
 
 use std::fmt::Debug;
 
-use tempfile::Builder;
-
 include!("bindings.rs");
 
+mod binary_copy;
+mod copy;
+mod instance_data;
+mod pipeline;
+mod statement;
+mod transaction;
+pub use binary_copy::{BinaryCopyReader, BinaryCopyWriter, BinaryRow, FromBinary, ToBinary};
+pub use copy::copy_escape_field;
+pub use statement::PreparedStatement;
+use statement::StatementCache;
+pub use transaction::{Savepoint, Transaction};
+
+#[cfg(feature = "pool")]
+mod pool;
+#[cfg(feature = "pool")]
+pub use pool::{PgPool, PooledConn};
+
+#[cfg(feature = "tokio")]
+mod async_conn;
+#[cfg(feature = "tokio")]
+pub use async_conn::AsyncPgConn;
+
+/// The `InvalidOid` value from `postgres_ext.h`, which bindgen doesn't pick up.
+pub const INVALID_OID: Oid = 0;
+
+// OIDs of well-known built-in types, as in the server's `pg_type_d.h`.
+pub const BOOLOID: Oid = 16;
+pub const BYTEAOID: Oid = 17;
+pub const CHAROID: Oid = 18;
+pub const NAMEOID: Oid = 19;
+pub const INT8OID: Oid = 20;
+pub const INT2OID: Oid = 21;
+pub const INT4OID: Oid = 23;
+pub const TEXTOID: Oid = 25;
+pub const OIDOID: Oid = 26;
+pub const JSONOID: Oid = 114;
+pub const XMLOID: Oid = 142;
+pub const FLOAT4OID: Oid = 700;
+pub const FLOAT8OID: Oid = 701;
+pub const UNKNOWNOID: Oid = 705;
+pub const BPCHAROID: Oid = 1042;
+pub const VARCHAROID: Oid = 1043;
+pub const DATEOID: Oid = 1082;
+pub const TIMEOID: Oid = 1083;
+pub const TIMESTAMPOID: Oid = 1114;
+pub const TIMESTAMPTZOID: Oid = 1184;
+pub const INTERVALOID: Oid = 1186;
+pub const NUMERICOID: Oid = 1700;
+pub const UUIDOID: Oid = 2950;
+pub const JSONBOID: Oid = 3802;
+
+/// The name of a well-known built-in type, handy when debugging [`PgResult::field_type`].
+///
+/// Returns `None` for any other OID; query `pg_type` to resolve those.
+pub fn type_name(oid: Oid) -> Option<&'static str> {
+    match oid {
+        BOOLOID => Some("bool"),
+        BYTEAOID => Some("bytea"),
+        CHAROID => Some("char"),
+        NAMEOID => Some("name"),
+        INT8OID => Some("int8"),
+        INT2OID => Some("int2"),
+        INT4OID => Some("int4"),
+        TEXTOID => Some("text"),
+        OIDOID => Some("oid"),
+        JSONOID => Some("json"),
+        XMLOID => Some("xml"),
+        FLOAT4OID => Some("float4"),
+        FLOAT8OID => Some("float8"),
+        UNKNOWNOID => Some("unknown"),
+        BPCHAROID => Some("bpchar"),
+        VARCHAROID => Some("varchar"),
+        DATEOID => Some("date"),
+        TIMEOID => Some("time"),
+        TIMESTAMPOID => Some("timestamp"),
+        TIMESTAMPTZOID => Some("timestamptz"),
+        INTERVALOID => Some("interval"),
+        NUMERICOID => Some("numeric"),
+        UUIDOID => Some("uuid"),
+        JSONBOID => Some("jsonb"),
+        _ => None,
+    }
+}
+
+/// Whether the linked libpq was built thread-safe, which sharing connections across threads
+/// relies on; always true since PostgreSQL 17.
+/// See also [PQisthreadsafe](https://www.postgresql.org/docs/current/libpq-threading.html#LIBPQ-PQISTHREADSAFE).
+pub fn is_threadsafe() -> bool {
+    unsafe { PQisthreadsafe() == 1 }
+}
+
+/// The socket of a connection, as returned by [`PgConn::socket`].
+///
+/// The file descriptor is owned by libpq: it must not be closed by the caller, and it is
+/// only valid until the connection is reset or dropped.
 pub struct PgSocket {
     socket: i32,
 }
 
+#[cfg(unix)]
+impl std::os::fd::AsRawFd for PgSocket {
+    /// The raw descriptor, which is `-1` if the connection has no open socket.
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        self.socket
+    }
+}
+
+#[cfg(unix)]
+impl std::os::fd::AsFd for PgSocket {
+    /// # Panics
+    ///
+    /// If the connection has no open socket.
+    fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+        assert_ne!(self.socket, -1, "The connection has no open socket.");
+        unsafe { std::os::fd::BorrowedFd::borrow_raw(self.socket) }
+    }
+}
+
 pub enum PgSocketPollResult {
     Timeout,
     Error(String),
@@ -31,6 +158,338 @@ impl Display for PgSocketPollResult {
     }
 }
 
+/// The status of a connection, mirroring `ConnStatusType`.
+///
+/// See the [official doc](https://www.postgresql.org/docs/current/libpq-status.html#LIBPQ-PQSTATUS).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnStatus {
+    Ok,
+    Bad,
+    Started,
+    Made,
+    AwaitingResponse,
+    AuthOk,
+    Setenv,
+    SslStartup,
+    Needed,
+    CheckWritable,
+    Consume,
+    GssStartup,
+    CheckTarget,
+    CheckStandby,
+    Allocated,
+    Authenticating,
+    /// A value not known to this version of the crate.
+    Unknown(ConnStatusType),
+}
+
+impl ConnStatus {
+    /// The name of the `ConnStatusType` value, e.g. `"CONNECTION_OK"`, or `"Unknown"` for a
+    /// value not known to this version of the crate. Unlike for results, libpq has no
+    /// function for this.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConnStatus::Ok => "CONNECTION_OK",
+            ConnStatus::Bad => "CONNECTION_BAD",
+            ConnStatus::Started => "CONNECTION_STARTED",
+            ConnStatus::Made => "CONNECTION_MADE",
+            ConnStatus::AwaitingResponse => "CONNECTION_AWAITING_RESPONSE",
+            ConnStatus::AuthOk => "CONNECTION_AUTH_OK",
+            ConnStatus::Setenv => "CONNECTION_SETENV",
+            ConnStatus::SslStartup => "CONNECTION_SSL_STARTUP",
+            ConnStatus::Needed => "CONNECTION_NEEDED",
+            ConnStatus::CheckWritable => "CONNECTION_CHECK_WRITABLE",
+            ConnStatus::Consume => "CONNECTION_CONSUME",
+            ConnStatus::GssStartup => "CONNECTION_GSS_STARTUP",
+            ConnStatus::CheckTarget => "CONNECTION_CHECK_TARGET",
+            ConnStatus::CheckStandby => "CONNECTION_CHECK_STANDBY",
+            ConnStatus::Allocated => "CONNECTION_ALLOCATED",
+            ConnStatus::Authenticating => "CONNECTION_AUTHENTICATING",
+            ConnStatus::Unknown(_) => "Unknown",
+        }
+    }
+}
+
+impl Display for ConnStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<ConnStatusType> for ConnStatus {
+    #[allow(non_upper_case_globals)]
+    fn from(status: ConnStatusType) -> Self {
+        match status {
+            ConnStatusType_CONNECTION_OK => ConnStatus::Ok,
+            ConnStatusType_CONNECTION_BAD => ConnStatus::Bad,
+            ConnStatusType_CONNECTION_STARTED => ConnStatus::Started,
+            ConnStatusType_CONNECTION_MADE => ConnStatus::Made,
+            ConnStatusType_CONNECTION_AWAITING_RESPONSE => ConnStatus::AwaitingResponse,
+            ConnStatusType_CONNECTION_AUTH_OK => ConnStatus::AuthOk,
+            ConnStatusType_CONNECTION_SETENV => ConnStatus::Setenv,
+            ConnStatusType_CONNECTION_SSL_STARTUP => ConnStatus::SslStartup,
+            ConnStatusType_CONNECTION_NEEDED => ConnStatus::Needed,
+            ConnStatusType_CONNECTION_CHECK_WRITABLE => ConnStatus::CheckWritable,
+            ConnStatusType_CONNECTION_CONSUME => ConnStatus::Consume,
+            ConnStatusType_CONNECTION_GSS_STARTUP => ConnStatus::GssStartup,
+            ConnStatusType_CONNECTION_CHECK_TARGET => ConnStatus::CheckTarget,
+            ConnStatusType_CONNECTION_CHECK_STANDBY => ConnStatus::CheckStandby,
+            ConnStatusType_CONNECTION_ALLOCATED => ConnStatus::Allocated,
+            ConnStatusType_CONNECTION_AUTHENTICATING => ConnStatus::Authenticating,
+            other => ConnStatus::Unknown(other),
+        }
+    }
+}
+
+impl From<ConnStatus> for ConnStatusType {
+    fn from(status: ConnStatus) -> Self {
+        match status {
+            ConnStatus::Ok => ConnStatusType_CONNECTION_OK,
+            ConnStatus::Bad => ConnStatusType_CONNECTION_BAD,
+            ConnStatus::Started => ConnStatusType_CONNECTION_STARTED,
+            ConnStatus::Made => ConnStatusType_CONNECTION_MADE,
+            ConnStatus::AwaitingResponse => ConnStatusType_CONNECTION_AWAITING_RESPONSE,
+            ConnStatus::AuthOk => ConnStatusType_CONNECTION_AUTH_OK,
+            ConnStatus::Setenv => ConnStatusType_CONNECTION_SETENV,
+            ConnStatus::SslStartup => ConnStatusType_CONNECTION_SSL_STARTUP,
+            ConnStatus::Needed => ConnStatusType_CONNECTION_NEEDED,
+            ConnStatus::CheckWritable => ConnStatusType_CONNECTION_CHECK_WRITABLE,
+            ConnStatus::Consume => ConnStatusType_CONNECTION_CONSUME,
+            ConnStatus::GssStartup => ConnStatusType_CONNECTION_GSS_STARTUP,
+            ConnStatus::CheckTarget => ConnStatusType_CONNECTION_CHECK_TARGET,
+            ConnStatus::CheckStandby => ConnStatusType_CONNECTION_CHECK_STANDBY,
+            ConnStatus::Allocated => ConnStatusType_CONNECTION_ALLOCATED,
+            ConnStatus::Authenticating => ConnStatusType_CONNECTION_AUTHENTICATING,
+            ConnStatus::Unknown(other) => other,
+        }
+    }
+}
+
+/// The status of a result, mirroring `ExecStatusType`.
+///
+/// See the [official doc](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQRESULTSTATUS).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecStatus {
+    EmptyQuery,
+    CommandOk,
+    TuplesOk,
+    CopyOut,
+    CopyIn,
+    BadResponse,
+    NonfatalError,
+    FatalError,
+    CopyBoth,
+    SingleTuple,
+    PipelineSync,
+    PipelineAborted,
+    TuplesChunk,
+    /// A value not known to this version of the crate.
+    Unknown(ExecStatusType),
+}
+
+impl From<ExecStatusType> for ExecStatus {
+    #[allow(non_upper_case_globals)]
+    fn from(status: ExecStatusType) -> Self {
+        match status {
+            ExecStatusType_PGRES_EMPTY_QUERY => ExecStatus::EmptyQuery,
+            ExecStatusType_PGRES_COMMAND_OK => ExecStatus::CommandOk,
+            ExecStatusType_PGRES_TUPLES_OK => ExecStatus::TuplesOk,
+            ExecStatusType_PGRES_COPY_OUT => ExecStatus::CopyOut,
+            ExecStatusType_PGRES_COPY_IN => ExecStatus::CopyIn,
+            ExecStatusType_PGRES_BAD_RESPONSE => ExecStatus::BadResponse,
+            ExecStatusType_PGRES_NONFATAL_ERROR => ExecStatus::NonfatalError,
+            ExecStatusType_PGRES_FATAL_ERROR => ExecStatus::FatalError,
+            ExecStatusType_PGRES_COPY_BOTH => ExecStatus::CopyBoth,
+            ExecStatusType_PGRES_SINGLE_TUPLE => ExecStatus::SingleTuple,
+            ExecStatusType_PGRES_PIPELINE_SYNC => ExecStatus::PipelineSync,
+            ExecStatusType_PGRES_PIPELINE_ABORTED => ExecStatus::PipelineAborted,
+            ExecStatusType_PGRES_TUPLES_CHUNK => ExecStatus::TuplesChunk,
+            other => ExecStatus::Unknown(other),
+        }
+    }
+}
+
+impl From<ExecStatus> for ExecStatusType {
+    fn from(status: ExecStatus) -> Self {
+        match status {
+            ExecStatus::EmptyQuery => ExecStatusType_PGRES_EMPTY_QUERY,
+            ExecStatus::CommandOk => ExecStatusType_PGRES_COMMAND_OK,
+            ExecStatus::TuplesOk => ExecStatusType_PGRES_TUPLES_OK,
+            ExecStatus::CopyOut => ExecStatusType_PGRES_COPY_OUT,
+            ExecStatus::CopyIn => ExecStatusType_PGRES_COPY_IN,
+            ExecStatus::BadResponse => ExecStatusType_PGRES_BAD_RESPONSE,
+            ExecStatus::NonfatalError => ExecStatusType_PGRES_NONFATAL_ERROR,
+            ExecStatus::FatalError => ExecStatusType_PGRES_FATAL_ERROR,
+            ExecStatus::CopyBoth => ExecStatusType_PGRES_COPY_BOTH,
+            ExecStatus::SingleTuple => ExecStatusType_PGRES_SINGLE_TUPLE,
+            ExecStatus::PipelineSync => ExecStatusType_PGRES_PIPELINE_SYNC,
+            ExecStatus::PipelineAborted => ExecStatusType_PGRES_PIPELINE_ABORTED,
+            ExecStatus::TuplesChunk => ExecStatusType_PGRES_TUPLES_CHUNK,
+            ExecStatus::Unknown(other) => other,
+        }
+    }
+}
+
+/// How much detail error and notice messages contain, mirroring `PGVerbosity`.
+///
+/// See the [official doc](https://www.postgresql.org/docs/current/libpq-control.html#LIBPQ-PQSETERRORVERBOSITY).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verbosity {
+    /// Only the severity, primary text and position.
+    Terse,
+    /// Also the detail, hint and context lines.
+    Default,
+    /// All the available fields.
+    Verbose,
+    /// Only the severity and SQLSTATE code.
+    Sqlstate,
+}
+
+impl From<Verbosity> for PGVerbosity {
+    fn from(verbosity: Verbosity) -> Self {
+        match verbosity {
+            Verbosity::Terse => PGVerbosity_PQERRORS_TERSE,
+            Verbosity::Default => PGVerbosity_PQERRORS_DEFAULT,
+            Verbosity::Verbose => PGVerbosity_PQERRORS_VERBOSE,
+            Verbosity::Sqlstate => PGVerbosity_PQERRORS_SQLSTATE,
+        }
+    }
+}
+
+impl From<PGVerbosity> for Verbosity {
+    /// Values unknown to this version of the crate are taken as [`Verbosity::Default`].
+    #[allow(non_upper_case_globals)]
+    fn from(verbosity: PGVerbosity) -> Self {
+        match verbosity {
+            PGVerbosity_PQERRORS_TERSE => Verbosity::Terse,
+            PGVerbosity_PQERRORS_VERBOSE => Verbosity::Verbose,
+            PGVerbosity_PQERRORS_SQLSTATE => Verbosity::Sqlstate,
+            _ => Verbosity::Default,
+        }
+    }
+}
+
+/// When error and notice messages include the `CONTEXT` field, mirroring
+/// `PGContextVisibility`.
+///
+/// See the [official doc](https://www.postgresql.org/docs/current/libpq-control.html#LIBPQ-PQSETERRORCONTEXTVISIBILITY).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextVisibility {
+    Never,
+    /// Only for errors, the default.
+    Errors,
+    Always,
+}
+
+impl From<ContextVisibility> for PGContextVisibility {
+    fn from(visibility: ContextVisibility) -> Self {
+        match visibility {
+            ContextVisibility::Never => PGContextVisibility_PQSHOW_CONTEXT_NEVER,
+            ContextVisibility::Errors => PGContextVisibility_PQSHOW_CONTEXT_ERRORS,
+            ContextVisibility::Always => PGContextVisibility_PQSHOW_CONTEXT_ALWAYS,
+        }
+    }
+}
+
+impl From<PGContextVisibility> for ContextVisibility {
+    /// Values unknown to this version of the crate are taken as [`ContextVisibility::Errors`].
+    #[allow(non_upper_case_globals)]
+    fn from(visibility: PGContextVisibility) -> Self {
+        match visibility {
+            PGContextVisibility_PQSHOW_CONTEXT_NEVER => ContextVisibility::Never,
+            PGContextVisibility_PQSHOW_CONTEXT_ALWAYS => ContextVisibility::Always,
+            _ => ContextVisibility::Errors,
+        }
+    }
+}
+
+/// Errors reported by the safe wrappers in this crate.
+#[derive(Debug)]
+pub enum PgError {
+    /// A string passed to libpq contained an interior nul byte.
+    Nul(NulError),
+    /// An I/O operation performed by the wrapper failed.
+    Io(std::io::Error),
+    /// No column with the given index or name exists in the result.
+    ColumnNotFound(String),
+    /// No row with the given index exists in the result.
+    RowNotFound(i32),
+    /// The value at the given position is SQL NULL where a value was expected.
+    UnexpectedNull { row: i32, col: i32 },
+    /// The text value at the given position could not be converted to the requested type.
+    InvalidValue { row: i32, col: i32, value: String },
+    /// The query returned this number of rows, where one (or at most one) was expected.
+    UnexpectedRowCount(i32),
+    /// The connection reported an error, carrying its [`PgConn::error_message`].
+    Connection(String),
+    /// The operation didn't complete within the given time.
+    Timeout,
+    /// The command started a COPY, with this status (`PGRES_COPY_IN`, `PGRES_COPY_OUT` or
+    /// `PGRES_COPY_BOTH`): its data must now be transferred, see [`PgResult::check`].
+    CopyInProgress(ExecStatusType),
+    /// The server reported a failed command, see [`PgResult::check`].
+    Query {
+        status: ExecStatusType,
+        message: String,
+        sqlstate: Option<String>,
+    },
+}
+
+impl Display for PgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgError::Nul(e) => write!(f, "Nul error: {}", e),
+            PgError::Io(e) => write!(f, "IO error: {}", e),
+            PgError::ColumnNotFound(c) => write!(f, "Column not found: {}", c),
+            PgError::RowNotFound(row) => write!(f, "Row not found: {}", row),
+            PgError::UnexpectedNull { row, col } => {
+                write!(f, "Unexpected NULL at row {}, column {}", row, col)
+            }
+            PgError::InvalidValue { row, col, value } => write!(
+                f,
+                "Invalid value {:?} at row {}, column {}",
+                value, row, col
+            ),
+            PgError::UnexpectedRowCount(n) => write!(f, "Unexpected number of rows: {}", n),
+            PgError::Connection(message) => write!(f, "Connection error: {}", message.trim_end()),
+            PgError::Timeout => write!(f, "Timed out"),
+            PgError::CopyInProgress(status) => {
+                let status = unsafe { std::ffi::CStr::from_ptr(PQresStatus(*status)) };
+                write!(f, "COPY in progress: {}", status.to_string_lossy())
+            }
+            PgError::Query {
+                status, message, ..
+            } if message.is_empty() => {
+                let status = unsafe { std::ffi::CStr::from_ptr(PQresStatus(*status)) };
+                write!(f, "Query failed: {}", status.to_string_lossy())
+            }
+            PgError::Query { message, .. } => write!(f, "Query failed: {}", message.trim_end()),
+        }
+    }
+}
+
+impl std::error::Error for PgError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PgError::Nul(e) => Some(e),
+            PgError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<NulError> for PgError {
+    fn from(e: NulError) -> Self {
+        PgError::Nul(e)
+    }
+}
+
+impl From<std::io::Error> for PgError {
+    fn from(e: std::io::Error) -> Self {
+        PgError::Io(e)
+    }
+}
+
 impl PgSocket {
     pub fn poll(
         &self,
@@ -44,28 +503,88 @@ impl PgSocket {
                 None => -1,
             };
 
-            match PQsocketPoll(self.socket, read.into(), write.into(), timeout_ms) {
-                a if a > 0 => Ok(()),
-                0 => Err(PgSocketPollResult::Timeout),
-                _ => Err(PgSocketPollResult::Error(
-                    std::io::Error::last_os_error().to_string(),
-                )),
-            }
+            Self::poll_until(self.socket, read, write, timeout_ms)
+        }
+    }
+
+    /// Like [`PgSocket::poll`], with the timeout given as a [`Duration`](std::time::Duration),
+    /// converted to whole microseconds without going through floating point.
+    /// See also [PQsocketPoll](https://www.postgresql.org/docs/current/libpq-connect.html#LIBPQ-PQSOCKETPOLL).
+    pub fn poll_duration(
+        &self,
+        read: bool,
+        write: bool,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<(), PgSocketPollResult> {
+        unsafe {
+            let end_time = match timeout {
+                Some(t) => PQgetCurrentTimeUSec()
+                    .saturating_add(i64::try_from(t.as_micros()).unwrap_or(i64::MAX)),
+                None => -1,
+            };
+
+            Self::poll_until(self.socket, read, write, end_time)
+        }
+    }
+
+    fn poll_until(
+        socket: i32,
+        read: bool,
+        write: bool,
+        end_time: pg_usec_time_t,
+    ) -> Result<(), PgSocketPollResult> {
+        match unsafe { PQsocketPoll(socket, read.into(), write.into(), end_time) } {
+            a if a > 0 => Ok(()),
+            0 => Err(PgSocketPollResult::Timeout),
+            _ => Err(PgSocketPollResult::Error(
+                std::io::Error::last_os_error().to_string(),
+            )),
         }
     }
 }
 pub struct PgConn {
     conn: *mut PGconn,
+    // Closures installed as notice processor/receiver, kept alive while libpq holds a pointer to them.
+    notice_processor: Option<Box<dyn Send>>,
+    notice_receiver: Option<Box<dyn Send>>,
+    // libpq's own notice processor, saved when the first custom one is installed.
+    default_notice_processor: PQnoticeProcessor,
+    // The stream opened by `trace`/`trace_fd`, closed by `untrace` or on drop.
+    trace_file: *mut FILE,
+    // Channels subscribed to via `listen`/`listen_many`, re-subscribed by `reconnect`.
+    listening: HashSet<String>,
+    // Statements prepared by `exec_cached`, forgotten by `reconnect`.
+    statement_cache: StatementCache,
 }
 
 unsafe impl Send for PgConn {}
 
 unsafe impl Sync for PgConn {}
 
+/// A notice processor replaced by [`PgConn::set_notice_processor`]: either a closure
+/// installed by it before, or libpq's default processor.
+pub struct PreviousNoticeProcessor {
+    func: PQnoticeProcessor,
+    closure: Option<Box<dyn Send>>,
+}
+
+/// A notice receiver replaced by [`PgConn::set_notice_receiver`]: either a closure
+/// installed by it before, or libpq's default receiver.
+pub struct PreviousNoticeReceiver {
+    func: PQnoticeReceiver,
+    closure: Option<Box<dyn Send>>,
+}
+
 pub struct PgResult {
     res: *mut PGresult,
 }
 
+// Results are read-only once created and independent of their connection, so libpq allows
+// passing them between threads.
+unsafe impl Send for PgResult {}
+
+unsafe impl Sync for PgResult {}
+
 pub struct PgNotify {
     notify: *mut PGnotify,
 }
@@ -78,6 +597,11 @@ impl PgNotify {
         }
     }
 
+    /// Like [`PgNotify::relname`], but failing on invalid UTF-8 instead of replacing it.
+    pub fn relname_strict(&self) -> Result<String, Utf8Error> {
+        unsafe { strict_string((*self.notify).relname) }
+    }
+
     pub fn be_pid(&self) -> i32 {
         unsafe { (*self.notify).be_pid }
     }
@@ -88,12 +612,53 @@ impl PgNotify {
             std::ffi::CStr::from_ptr(s).to_string_lossy().into_owned()
         }
     }
+
+    /// Like [`PgNotify::extra`], but failing on invalid UTF-8 instead of replacing it.
+    pub fn extra_strict(&self) -> Result<String, Utf8Error> {
+        unsafe { strict_string((*self.notify).extra) }
+    }
+}
+
+impl Debug for PgConn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PgConn")
+            .field("db", &self.db())
+            .field("user", &self.user())
+            .field("host", &self.host())
+            .field("port", &self.port())
+            .field("status", &self.status_enum())
+            .finish()
+    }
+}
+
+impl Debug for PgResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PgResult")
+            .field("status", &self.status_enum())
+            .field("ntuples", &self.ntuples())
+            .field("nfields", &self.nfields())
+            .finish()
+    }
+}
+
+impl Debug for PgNotify {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PgNotify")
+            .field("relname", &self.relname())
+            .field("be_pid", &self.be_pid())
+            .field("extra", &self.extra())
+            .finish()
+    }
 }
 
 impl Drop for PgConn {
     fn drop(&mut self) {
         unsafe {
             PQfinish(self.conn);
+            // Closed last, as PQfinish may still write to the trace.
+            if !self.trace_file.is_null() {
+                fclose(self.trace_file);
+            }
         }
     }
 }
@@ -114,6 +679,19 @@ impl Drop for PgResult {
     }
 }
 
+/// Copies the columns and rows of the result into an independently owned one.
+///
+/// As with [PQcopyResult](https://www.postgresql.org/docs/current/libpq-misc.html#LIBPQ-PQCOPYRESULT),
+/// the copy always has status `PGRES_TUPLES_OK` and the original command status, but no
+/// error message.
+impl Clone for PgResult {
+    fn clone(&self) -> Self {
+        let res = unsafe { PQcopyResult(self.res, (PG_COPYRES_ATTRS | PG_COPYRES_TUPLES) as i32) };
+        assert!(!res.is_null(), "Out of memory while copying a result.");
+        PgResult { res }
+    }
+}
+
 impl PgConn {
     /// Connect to the database using environment variables.
     ///
@@ -125,8 +703,89 @@ impl PgConn {
     pub fn connect_db(s: &str) -> Result<PgConn, NulError> {
         unsafe {
             let conninfo = std::ffi::CString::new(s)?;
-            let conn = PQconnectdb(conninfo.as_ptr());
-            Ok(PgConn { conn })
+            Ok(Self::from_raw(PQconnectdb(conninfo.as_ptr())))
+        }
+    }
+
+    /// Start connecting without blocking; drive the connection with [`PgConn::connect_poll`]
+    /// until it reports `PGRES_POLLING_OK` or `PGRES_POLLING_FAILED`.
+    /// See also [PQconnectStart](https://www.postgresql.org/docs/current/libpq-connect.html#LIBPQ-PQCONNECTSTART).
+    pub fn connect_start(s: &str) -> Result<PgConn, NulError> {
+        let conninfo = CString::new(s)?;
+        Ok(Self::from_raw(unsafe { PQconnectStart(conninfo.as_ptr()) }))
+    }
+
+    /// Advance a connection started with [`PgConn::connect_start`]. On `PGRES_POLLING_READING`
+    /// or `PGRES_POLLING_WRITING`, wait for the [socket](PgConn::socket), which may change
+    /// between calls, to be read-ready or write-ready before calling it again.
+    /// See also [PQconnectPoll](https://www.postgresql.org/docs/current/libpq-connect.html#LIBPQ-PQCONNECTPOLL).
+    pub fn connect_poll(&mut self) -> PostgresPollingStatusType {
+        unsafe { PQconnectPoll(self.conn) }
+    }
+
+    /// Connect, giving up with [`PgError::Timeout`] if the connection isn't established within
+    /// `timeout`.
+    ///
+    /// The connection is driven with [`PgConn::connect_poll`], waiting on its socket with a
+    /// deadline, rather than relying on libpq's own `connect_timeout`. The deadline bounds
+    /// those waits only: libpq resolves host names synchronously, within `PQconnectStart`
+    /// or `PQconnectPoll`, so a slow lookup can still overrun it. Give the address with
+    /// `hostaddr` to avoid the lookup.
+    #[allow(non_upper_case_globals)]
+    pub fn connect_with_timeout(
+        conninfo: &str,
+        timeout: std::time::Duration,
+    ) -> Result<PgConn, PgError> {
+        let deadline = std::time::Instant::now() + timeout;
+        let mut conn = PgConn::connect_start(conninfo)?;
+        if conn.is_bad() {
+            return Err(PgError::Connection(conn.error_message()));
+        }
+
+        // As the docs prescribe, start as if polling had asked to wait for writing.
+        let mut status = PostgresPollingStatusType_PGRES_POLLING_WRITING;
+        loop {
+            match status {
+                PostgresPollingStatusType_PGRES_POLLING_OK => return Ok(conn),
+                PostgresPollingStatusType_PGRES_POLLING_FAILED => {
+                    return Err(PgError::Connection(conn.error_message()));
+                }
+                PostgresPollingStatusType_PGRES_POLLING_READING
+                | PostgresPollingStatusType_PGRES_POLLING_WRITING => {
+                    let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+                    let reading = status == PostgresPollingStatusType_PGRES_POLLING_READING;
+                    match conn
+                        .socket()
+                        .poll_duration(reading, !reading, Some(remaining))
+                    {
+                        Ok(()) => {}
+                        Err(PgSocketPollResult::Timeout) => return Err(PgError::Timeout),
+                        Err(PgSocketPollResult::Error(message)) => {
+                            return Err(PgError::Connection(message));
+                        }
+                    }
+                }
+                _ => {}
+            }
+            status = conn.connect_poll();
+            // A single poll may block, e.g. resolving the next host name.
+            let pending = status == PostgresPollingStatusType_PGRES_POLLING_READING
+                || status == PostgresPollingStatusType_PGRES_POLLING_WRITING;
+            if pending && std::time::Instant::now() >= deadline {
+                return Err(PgError::Timeout);
+            }
+        }
+    }
+
+    fn from_raw(conn: *mut PGconn) -> PgConn {
+        PgConn {
+            conn,
+            notice_processor: None,
+            notice_receiver: None,
+            default_notice_processor: None,
+            trace_file: null_mut(),
+            listening: HashSet::new(),
+            statement_cache: StatementCache::new(),
         }
     }
 
@@ -134,6 +793,27 @@ impl PgConn {
         unsafe { PQstatus(self.conn) }
     }
 
+    /// Like [`PgConn::status`], as a [`ConnStatus`].
+    pub fn status_enum(&self) -> ConnStatus {
+        self.status().into()
+    }
+
+    /// The name of the status, e.g. `"CONNECTION_OK"`, see [`ConnStatus::as_str`].
+    pub fn status_str(&self) -> &'static str {
+        self.status_enum().as_str()
+    }
+
+    /// Whether the status is `CONNECTION_OK`.
+    pub fn is_ok(&self) -> bool {
+        self.status() == ConnStatusType_CONNECTION_OK
+    }
+
+    /// Whether the status is `CONNECTION_BAD`, i.e. connecting failed or the connection was
+    /// lost.
+    pub fn is_bad(&self) -> bool {
+        self.status() == ConnStatusType_CONNECTION_BAD
+    }
+
     pub fn exec(&self, query: &str) -> Result<PgResult, NulError> {
         unsafe {
             let c_query = std::ffi::CString::new(query)?;
@@ -142,24 +822,104 @@ impl PgConn {
         }
     }
 
-    pub fn exec_file(&self, file_path: &str) -> Result<PgResult, NulError> {
-        let content = std::fs::read_to_string(file_path).expect("Failed to read file.");
-        self.exec(&content)
+    /// Describe the named prepared statement; the result carries its parameter types
+    /// (see [`PgResult::nparams`]) and result columns, but no rows.
+    /// See also [PQdescribePrepared](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQDESCRIBEPREPARED).
+    pub fn describe_prepared(&self, name: &str) -> Result<PgResult, PgError> {
+        unsafe {
+            let c_name = std::ffi::CString::new(name)?;
+            let res = PQdescribePrepared(self.conn, c_name.as_ptr());
+            Ok(PgResult { res })
+        }
+    }
+
+    /// Execute the SQL contained in the given file.
+    pub fn exec_file(&self, file_path: &str) -> Result<PgResult, PgError> {
+        let content = std::fs::read_to_string(file_path)?;
+        Ok(self.exec(&content)?)
     }
 
-    pub fn trace(&mut self, file: &str) {
+    /// Trace the messages exchanged with the server to `file`, truncating it first; fails if
+    /// it cannot be opened for writing.
+    /// See also [PQtrace](https://www.postgresql.org/docs/current/libpq-control.html#LIBPQ-PQTRACE).
+    pub fn trace(&mut self, file: &str) -> Result<(), PgError> {
+        self.trace_file_mode(file, c"w")
+    }
+
+    /// Like [`PgConn::trace`], but append the trace to `file` instead of truncating it, e.g.
+    /// to keep the trace of a previous connection.
+    pub fn trace_append(&mut self, file: &str) -> Result<(), PgError> {
+        self.trace_file_mode(file, c"a")
+    }
+
+    fn trace_file_mode(&mut self, file: &str, mode: &std::ffi::CStr) -> Result<(), PgError> {
+        let c_file = CString::new(file)?;
         unsafe {
-            let c_file = std::ffi::CString::new(file).unwrap();
-            let mode = std::ffi::CString::new("w").unwrap();
             let fp = fopen(c_file.as_ptr(), mode.as_ptr());
+            if fp.is_null() {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            self.trace_stream(fp);
+        }
+        Ok(())
+    }
+
+    /// Like [`PgConn::trace`], but write the trace to an already open descriptor, e.g. a pipe
+    /// or a temporary file, starting at its current position.
+    ///
+    /// The descriptor is duplicated, so the caller keeps ownership of `fd`; everything traced
+    /// is flushed to it by [`PgConn::untrace`] at the latest.
+    #[cfg(unix)]
+    pub fn trace_fd(&mut self, fd: impl std::os::fd::AsFd) -> Result<(), PgError> {
+        use std::os::fd::{FromRawFd, IntoRawFd};
+
+        let dup = fd.as_fd().try_clone_to_owned()?.into_raw_fd();
+        unsafe {
+            let fp = fdopen(dup, c"w".as_ptr());
+            if fp.is_null() {
+                let e = std::io::Error::last_os_error();
+                drop(std::os::fd::OwnedFd::from_raw_fd(dup));
+                return Err(e.into());
+            }
+            self.trace_stream(fp);
+        }
+        Ok(())
+    }
+
+    unsafe fn trace_stream(&mut self, fp: *mut FILE) {
+        self.untrace();
+        unsafe {
             PQtrace(self.conn, fp);
             assert_eq!(fflush(fp), 0);
         }
+        self.trace_file = fp;
     }
 
+    /// Control the format of the trace: `suppress_timestamps` omits the timestamp of each
+    /// message and `regress_mode` also redacts fields such as OIDs and backend PIDs, making the
+    /// output deterministic. Must be called after [`PgConn::trace`], which resets the flags.
+    /// See also [PQsetTraceFlags](https://www.postgresql.org/docs/current/libpq-control.html#LIBPQ-PQSETTRACEFLAGS).
+    pub fn set_trace_flags(&mut self, suppress_timestamps: bool, regress_mode: bool) {
+        let mut flags = 0;
+        if suppress_timestamps {
+            flags |= PQTRACE_SUPPRESS_TIMESTAMPS;
+        }
+        if regress_mode {
+            flags |= PQTRACE_REGRESS_MODE;
+        }
+        unsafe {
+            PQsetTraceFlags(self.conn, flags as i32);
+        }
+    }
+
+    /// Stop tracing, closing the stream opened by [`PgConn::trace`] or [`PgConn::trace_fd`].
     pub fn untrace(&mut self) {
         unsafe {
             PQuntrace(self.conn);
+            if !self.trace_file.is_null() {
+                fclose(self.trace_file);
+                self.trace_file = null_mut();
+            }
         }
     }
 
@@ -181,167 +941,1251 @@ impl PgConn {
         }
     }
 
-    pub fn notifies(&mut self) -> Option<PgNotify> {
+    /// Submit a query without waiting for its results, which are then retrieved with
+    /// [`PgConn::get_result`].
+    /// See also [PQsendQuery](https://www.postgresql.org/docs/current/libpq-async.html#LIBPQ-PQSENDQUERY).
+    pub fn send_query(&mut self, query: &str) -> Result<(), PgError> {
+        let c_query = CString::new(query)?;
+        let sent = unsafe { PQsendQuery(self.conn, c_query.as_ptr()) };
+        self.check_sent(sent)
+    }
+
+    /// Submit a command with parameters, as [`PgConn::exec_params`] does, without waiting
+    /// for its results, which are then read with [`PgConn::get_result`].
+    /// See also [PQsendQueryParams](https://www.postgresql.org/docs/current/libpq-async.html#LIBPQ-PQSENDQUERYPARAMS).
+    pub fn send_query_params(&mut self, query: &str, params: &[&dyn ToSql]) -> Result<(), PgError> {
+        let c_query = CString::new(query)?;
+        let params = TextParams::new(params)?;
+        let values = params.values();
+
+        let sent = unsafe {
+            PQsendQueryParams(
+                self.conn,
+                c_query.as_ptr(),
+                values.len() as i32,
+                params.types.as_ptr(),
+                values.as_ptr(),
+                std::ptr::null(),
+                std::ptr::null(),
+                0,
+            )
+        };
+        self.check_sent(sent)
+    }
+
+    /// Submit a request to create the prepared statement `name`, without waiting for the
+    /// outcome, read with [`PgConn::get_result`]. Parameter types missing from
+    /// `param_types`, or given as [`INVALID_OID`], are inferred by the server.
+    /// See also [PQsendPrepare](https://www.postgresql.org/docs/current/libpq-async.html#LIBPQ-PQSENDPREPARE).
+    pub fn send_prepare(
+        &mut self,
+        name: &str,
+        query: &str,
+        param_types: &[Oid],
+    ) -> Result<(), PgError> {
+        let c_name = CString::new(name)?;
+        let c_query = CString::new(query)?;
+
+        let sent = unsafe {
+            PQsendPrepare(
+                self.conn,
+                c_name.as_ptr(),
+                c_query.as_ptr(),
+                param_types.len() as i32,
+                param_types.as_ptr(),
+            )
+        };
+        self.check_sent(sent)
+    }
+
+    /// Submit a request to execute the prepared statement `name` with the given parameters,
+    /// without waiting for its results, read with [`PgConn::get_result`].
+    /// See also [PQsendQueryPrepared](https://www.postgresql.org/docs/current/libpq-async.html#LIBPQ-PQSENDQUERYPREPARED).
+    pub fn send_query_prepared(
+        &mut self,
+        name: &str,
+        params: &[&dyn ToSql],
+    ) -> Result<(), PgError> {
+        let c_name = CString::new(name)?;
+        let params = TextParams::new(params)?;
+        let values = params.values();
+
+        let sent = unsafe {
+            PQsendQueryPrepared(
+                self.conn,
+                c_name.as_ptr(),
+                values.len() as i32,
+                values.as_ptr(),
+                std::ptr::null(),
+                std::ptr::null(),
+                0,
+            )
+        };
+        self.check_sent(sent)
+    }
+
+    /// Submit a request to describe the prepared statement `name`, as
+    /// [`PgConn::describe_prepared`] does, without waiting for the result, read with
+    /// [`PgConn::get_result`].
+    /// See also [PQsendDescribePrepared](https://www.postgresql.org/docs/current/libpq-async.html#LIBPQ-PQSENDDESCRIBEPREPARED).
+    pub fn send_describe_prepared(&mut self, name: &str) -> Result<(), PgError> {
+        let c_name = CString::new(name)?;
+        let sent = unsafe { PQsendDescribePrepared(self.conn, c_name.as_ptr()) };
+        self.check_sent(sent)
+    }
+
+    /// Submit a request to describe the portal `name`, e.g. a cursor, without waiting for
+    /// the result, read with [`PgConn::get_result`]; it carries the portal's result
+    /// columns, but no rows.
+    /// See also [PQsendDescribePortal](https://www.postgresql.org/docs/current/libpq-async.html#LIBPQ-PQSENDDESCRIBEPORTAL).
+    pub fn send_describe_portal(&mut self, name: &str) -> Result<(), PgError> {
+        let c_name = CString::new(name)?;
+        let sent = unsafe { PQsendDescribePortal(self.conn, c_name.as_ptr()) };
+        self.check_sent(sent)
+    }
+
+    /// The outcome of a `PQsend*` call, which returns 0 on failure.
+    fn check_sent(&self, sent: i32) -> Result<(), PgError> {
+        if sent == 0 {
+            Err(PgError::Connection(self.error_message()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The next result of a query submitted with one of the `send_*` methods, or `None` once
+    /// all of them have been returned. Blocks unless [`PgConn::is_busy`] is `false`.
+    /// See also [PQgetResult](https://www.postgresql.org/docs/current/libpq-async.html#LIBPQ-PQGETRESULT).
+    pub fn get_result(&mut self) -> Option<PgResult> {
         unsafe {
-            let notify = PQnotifies(self.conn);
-            if notify.is_null() {
+            let res = PQgetResult(self.conn);
+            if res.is_null() {
                 None
             } else {
-                Some(PgNotify { notify })
+                Some(PgResult { res })
             }
         }
     }
 
-    pub fn error_message(&self) -> String {
-        unsafe {
-            let s = PQerrorMessage(self.conn);
-            if s.is_null() {
-                "".to_string()
-            } else {
-                std::ffi::CStr::from_ptr(s).to_string_lossy().into_owned()
+    /// Execute several semicolon-separated statements and collect the result of each one, in
+    /// order, unlike [`PgConn::exec`] which only returns the last.
+    ///
+    /// The results are returned unchecked: once a statement fails, the server skips the
+    /// remaining ones, so the last result is the failing statement's. The results also stop
+    /// at a statement starting a COPY, whose data the caller must then transfer before
+    /// reading the results of the following statements, see [`PgConn::results`].
+    pub fn exec_many(&mut self, sql: &str) -> Result<Vec<PgResult>, PgError> {
+        self.send_query(sql)?;
+        Ok(self.results().collect())
+    }
+
+    /// The results of the queries submitted with the `send_*` methods, fetched with
+    /// [`PgConn::get_result`] until there are no more, e.g. one per statement of a
+    /// multi-statement [`PgConn::send_query`].
+    ///
+    /// A statement starting a COPY ends the iteration with its COPY result, as `PQexec`
+    /// does, since libpq keeps returning one while the copy is in progress: the caller must
+    /// then transfer the data, e.g. with [`PgConn::put_copy_data`] and
+    /// [`PgConn::put_copy_end`], and read the results that follow.
+    pub fn results(&mut self) -> impl Iterator<Item = PgResult> + '_ {
+        let mut copying = false;
+        std::iter::from_fn(move || {
+            if copying {
+                return None;
             }
-        }
+            let res = self.get_result()?;
+            copying = res.is_copy();
+            Some(res)
+        })
     }
 
-    pub fn notify(&mut self, channel: &str, payload: Option<&str>) -> Result<PgResult, NulError> {
-        let query = match payload {
-            Some(p) => format!("NOTIFY {}, '{}';", channel, p),
-            None => format!("NOTIFY {};", channel),
-        };
-        self.exec(&query)
+    /// Switch the query just submitted with one of the `send_*` methods to chunked rows mode,
+    /// where its rows are returned in results of up to `chunk_size` rows each, with status
+    /// `PGRES_TUPLES_CHUNK`, followed by a final `PGRES_TUPLES_OK` result with no rows.
+    ///
+    /// Returns `false` if the mode could not be set, e.g. because no query is in progress.
+    /// See also [PQsetChunkedRowsMode](https://www.postgresql.org/docs/current/libpq-single-row-mode.html#LIBPQ-PQSETCHUNKEDROWSMODE).
+    pub fn set_chunked_rows_mode(&mut self, chunk_size: i32) -> bool {
+        unsafe { PQsetChunkedRowsMode(self.conn, chunk_size) == 1 }
     }
 
-    pub fn listen(&mut self, channel: &str) -> Result<PgResult, NulError> {
-        let query = format!("LISTEN {};", channel);
-        self.exec(&query)
+    /// The results of the submitted query, fetched with [`PgConn::get_result`] until there
+    /// are no more, such as the chunks produced in [chunked rows mode](PgConn::set_chunked_rows_mode).
+    pub fn chunked_results(&mut self) -> impl Iterator<Item = PgResult> + '_ {
+        self.results()
     }
 
+    /// Whether [`PgConn::get_result`] would block waiting for input from the server.
     ///
-    /// A callback function to receive notices from the server.
-    /// https://stackoverflow.com/questions/24191249/working-with-c-void-in-an-ffi
-    /// https://adventures.michaelfbryan.com/posts/rust-closures-in-ffi/
-    extern "C" fn ffi_notice_processor<F>(arg: *mut c_void, data: *const c_char)
-    where
-        F: FnMut(String),
-    {
-        unsafe {
-            let s = std::ffi::CStr::from_ptr(data)
-                .to_string_lossy()
-                .into_owned();
+    /// The canonical non-blocking way to wait for the results of a `send_*` call is:
+    ///
+    /// 1. wait for the socket to become readable with [`PgSocket::poll`];
+    /// 2. call [`PgConn::consume_input`];
+    /// 3. if `is_busy()` is still `true`, go back to 1.; otherwise call
+    ///    [`PgConn::get_result`], which now returns without blocking, and repeat from 3.
+    ///    until it returns `None`.
+    ///
+    /// See also [PQisBusy](https://www.postgresql.org/docs/current/libpq-async.html#LIBPQ-PQISBUSY).
+    pub fn is_busy(&self) -> bool {
+        unsafe { PQisBusy(self.conn) == 1 }
+    }
 
-            let f = &mut *(arg as *mut F);
+    /// Run the loop described on [`PgConn::is_busy`] up to `timeout` (forever if `None`),
+    /// returning whether [`PgConn::get_result`] can now be called without blocking, `false`
+    /// if the timeout elapsed first.
+    pub fn wait_ready(&mut self, timeout: Option<std::time::Duration>) -> Result<bool, PgError> {
+        let deadline = timeout.map(|t| std::time::Instant::now() + t);
+        self.consume_input().map_err(PgError::Connection)?;
+        while self.is_busy() {
+            let remaining =
+                deadline.map(|d| d.saturating_duration_since(std::time::Instant::now()));
+            match self.socket().poll_duration(true, false, remaining) {
+                Ok(()) => self.consume_input().map_err(PgError::Connection)?,
+                Err(PgSocketPollResult::Timeout) => return Ok(false),
+                Err(PgSocketPollResult::Error(message)) => {
+                    return Err(PgError::Connection(message));
+                }
+            }
+        }
+        Ok(true)
+    }
 
-            f(s);
+    /// Put the connection in non-blocking mode, where the `send_*` methods and
+    /// [`PgConn::flush`] return instead of blocking when the socket is not write-ready.
+    /// See also [PQsetnonblocking](https://www.postgresql.org/docs/current/libpq-async.html#LIBPQ-PQSETNONBLOCKING).
+    pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<(), PgError> {
+        if unsafe { PQsetnonblocking(self.conn, nonblocking as i32) } == 0 {
+            Ok(())
+        } else {
+            Err(PgError::Connection(self.error_message()))
         }
     }
 
-    pub fn set_notice_processor<F>(&mut self, proc: F) -> Box<F>
-    where
-        F: FnMut(String),
-    {
-        unsafe {
-            let mut b = Box::new(proc);
-            let a = b.as_mut() as *mut F as *mut c_void;
-            PQsetNoticeProcessor(self.conn, Some(Self::ffi_notice_processor::<F>), a);
-            b
+    /// See also [PQisnonblocking](https://www.postgresql.org/docs/current/libpq-async.html#LIBPQ-PQISNONBLOCKING).
+    pub fn is_nonblocking(&self) -> bool {
+        unsafe { PQisnonblocking(self.conn) == 1 }
+    }
+
+    /// Try to send any data queued for the server, returning `true` once the queue is empty
+    /// and `false` if, in non-blocking mode, some data remains to be sent: wait for the
+    /// socket to be write-ready (consuming input if it is read-ready) and call it again.
+    /// See also [PQflush](https://www.postgresql.org/docs/current/libpq-async.html#LIBPQ-PQFLUSH).
+    pub fn flush(&mut self) -> Result<bool, PgError> {
+        match unsafe { PQflush(self.conn) } {
+            0 => Ok(true),
+            1 => Ok(false),
+            _ => Err(PgError::Connection(self.error_message())),
+        }
+    }
+
+    pub fn notifies(&mut self) -> Option<PgNotify> {
+        unsafe {
+            let notify = PQnotifies(self.conn);
+            if notify.is_null() {
+                None
+            } else {
+                Some(PgNotify { notify })
+            }
+        }
+    }
+
+    /// The notifications already received, yielded until none is left, without reading
+    /// more input: call [`PgConn::consume_input`] first to pick up new ones.
+    pub fn drain_notifications(&mut self) -> impl Iterator<Item = PgNotify> + '_ {
+        std::iter::from_fn(|| self.notifies())
+    }
+
+    /// Wait for the next notification, up to `timeout` (forever if `None`), returning
+    /// `Ok(None)` if none arrived in time.
+    ///
+    /// A notification already received is returned right away; otherwise the socket is
+    /// polled and input consumed until one arrives.
+    pub fn wait_for_notification(
+        &mut self,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<Option<PgNotify>, PgError> {
+        let deadline = timeout.map(|t| std::time::Instant::now() + t);
+        loop {
+            if let Some(notify) = self.notifies() {
+                return Ok(Some(notify));
+            }
+
+            let remaining =
+                deadline.map(|d| d.saturating_duration_since(std::time::Instant::now()));
+            match self.socket().poll_duration(true, false, remaining) {
+                Ok(()) => self.consume_input().map_err(PgError::Connection)?,
+                Err(PgSocketPollResult::Timeout) => return Ok(None),
+                Err(PgSocketPollResult::Error(message)) => {
+                    return Err(PgError::Connection(message));
+                }
+            }
+        }
+    }
+
+    /// [Listen](PgConn::listen) on `channel` and wait for up to `count` notifications on it,
+    /// returning the channel name, payload and sending backend's PID of each, in order.
+    ///
+    /// Fewer are returned if `timeout` (overall, forever if `None`) elapses first.
+    /// Notifications on other channels received in the meantime are skipped. The
+    /// connection keeps listening on `channel` afterwards.
+    pub fn collect_notifications(
+        &mut self,
+        channel: &str,
+        count: usize,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<Vec<(String, String, i32)>, PgError> {
+        self.listen(channel)?.check()?;
+
+        let deadline = timeout.map(|t| std::time::Instant::now() + t);
+        let mut notifications = Vec::new();
+        while notifications.len() < count {
+            let remaining =
+                deadline.map(|d| d.saturating_duration_since(std::time::Instant::now()));
+            match self.wait_for_notification(remaining)? {
+                Some(notify) if notify.relname() == channel => {
+                    notifications.push((notify.relname(), notify.extra(), notify.be_pid()));
+                }
+                Some(_) => {}
+                None => break,
+            }
+        }
+        Ok(notifications)
+    }
+
+    /// The database name of the connection.
+    /// See also [PQdb](https://www.postgresql.org/docs/current/libpq-status.html#LIBPQ-PQDB).
+    pub fn db(&self) -> Option<String> {
+        unsafe { opt_string(PQdb(self.conn)) }
+    }
+
+    /// The user name of the connection.
+    /// See also [PQuser](https://www.postgresql.org/docs/current/libpq-status.html#LIBPQ-PQUSER).
+    pub fn user(&self) -> Option<String> {
+        unsafe { opt_string(PQuser(self.conn)) }
+    }
+
+    /// The server host name (or socket directory) of the connection.
+    /// See also [PQhost](https://www.postgresql.org/docs/current/libpq-status.html#LIBPQ-PQHOST).
+    pub fn host(&self) -> Option<String> {
+        unsafe { opt_string(PQhost(self.conn)) }
+    }
+
+    /// The port of the connection.
+    /// See also [PQport](https://www.postgresql.org/docs/current/libpq-status.html#LIBPQ-PQPORT).
+    pub fn port(&self) -> Option<String> {
+        unsafe { opt_string(PQport(self.conn)) }
+    }
+
+    /// The OpenSSL `SSL*` of the connection, as an opaque pointer, or `None` if SSL is not
+    /// in use, e.g. to inspect or pin the server certificate with OpenSSL bindings.
+    ///
+    /// The object is owned by libpq and only valid while the connection is open and not
+    /// reset; it must not be freed, nor used to read or write on the connection.
+    /// See also [PQgetssl](https://www.postgresql.org/docs/current/libpq-status.html#LIBPQ-PQGETSSL).
+    pub fn ssl_object(&self) -> Option<*mut c_void> {
+        let ssl = unsafe { PQgetssl(self.conn) };
+        if ssl.is_null() { None } else { Some(ssl) }
+    }
+
+    /// The connection options in effect, one per option libpq knows of, including the
+    /// defaults and those taken from environment variables or service files.
+    ///
+    /// The value of options meant to be hidden, such as the password, is replaced by an
+    /// empty string.
+    /// See also [PQconninfo](https://www.postgresql.org/docs/current/libpq-connect.html#LIBPQ-PQCONNINFO).
+    pub fn conninfo(&self) -> Vec<ConnInfoOption> {
+        let mut options = Vec::new();
+        unsafe {
+            let info = PQconninfo(self.conn);
+            if info.is_null() {
+                return options;
+            }
+            let mut option = info;
+            while !(*option).keyword.is_null() {
+                let o = &*option;
+                let dispchar = opt_string(o.dispchar).unwrap_or_default();
+                let value = opt_string(o.val);
+                options.push(ConnInfoOption {
+                    keyword: opt_string(o.keyword).unwrap_or_default(),
+                    envvar: opt_string(o.envvar),
+                    compiled: opt_string(o.compiled),
+                    value: if dispchar == "*" {
+                        value.map(|_| String::new())
+                    } else {
+                        value
+                    },
+                    label: opt_string(o.label).unwrap_or_default(),
+                    dispchar,
+                    dispsize: o.dispsize,
+                });
+                option = option.add(1);
+            }
+            PQconninfoFree(info);
+        }
+        options
+    }
+
+    pub fn error_message(&self) -> String {
+        unsafe {
+            let s = PQerrorMessage(self.conn);
+            if s.is_null() {
+                "".to_string()
+            } else {
+                std::ffi::CStr::from_ptr(s).to_string_lossy().into_owned()
+            }
+        }
+    }
+
+    /// Like [`PgConn::error_message`], but failing on invalid UTF-8 instead of replacing it.
+    pub fn error_message_strict(&self) -> Result<String, Utf8Error> {
+        unsafe { strict_string(PQerrorMessage(self.conn)) }
+    }
+
+    /// Set how much detail the messages of later errors and notices contain, returning the
+    /// previous setting.
+    /// See also [PQsetErrorVerbosity](https://www.postgresql.org/docs/current/libpq-control.html#LIBPQ-PQSETERRORVERBOSITY).
+    pub fn set_error_verbosity(&mut self, verbosity: Verbosity) -> Verbosity {
+        unsafe { PQsetErrorVerbosity(self.conn, verbosity.into()) }.into()
+    }
+
+    /// Set when the messages of later errors and notices include the `CONTEXT` field,
+    /// returning the previous setting.
+    /// See also [PQsetErrorContextVisibility](https://www.postgresql.org/docs/current/libpq-control.html#LIBPQ-PQSETERRORCONTEXTVISIBILITY).
+    pub fn set_error_context_visibility(
+        &mut self,
+        visibility: ContextVisibility,
+    ) -> ContextVisibility {
+        unsafe { PQsetErrorContextVisibility(self.conn, visibility.into()) }.into()
+    }
+
+    /// Whether the server asked for a password that wasn't available, typically checked after
+    /// a `CONNECTION_BAD` status to decide whether to prompt the user and try again.
+    /// See also [PQconnectionNeedsPassword](https://www.postgresql.org/docs/current/libpq-status.html#LIBPQ-PQCONNECTIONNEEDSPASSWORD).
+    pub fn connection_needs_password(&self) -> bool {
+        unsafe { PQconnectionNeedsPassword(self.conn) == 1 }
+    }
+
+    /// Whether password authentication was used for the connection.
+    /// See also [PQconnectionUsedPassword](https://www.postgresql.org/docs/current/libpq-status.html#LIBPQ-PQCONNECTIONUSEDPASSWORD).
+    pub fn connection_used_password(&self) -> bool {
+        unsafe { PQconnectionUsedPassword(self.conn) == 1 }
+    }
+
+    /// The current value of a parameter reported by the server, such as `server_version`,
+    /// `application_name` or `TimeZone`, or `None` if it is not known.
+    /// See also [PQparameterStatus](https://www.postgresql.org/docs/current/libpq-status.html#LIBPQ-PQPARAMETERSTATUS).
+    pub fn parameter_status(&self, name: &str) -> Result<Option<String>, NulError> {
+        let c_name = CString::new(name)?;
+        unsafe {
+            let s = PQparameterStatus(self.conn, c_name.as_ptr());
+            if s.is_null() {
+                Ok(None)
+            } else {
+                Ok(Some(
+                    std::ffi::CStr::from_ptr(s).to_string_lossy().into_owned(),
+                ))
+            }
+        }
+    }
+
+    /// The algorithm the server hashes new passwords with, the `password_encryption`
+    /// setting read with `SHOW`, e.g. `scram-sha-256`.
+    ///
+    /// This is also the algorithm that [`PgConn::encrypt_password`] uses by default.
+    pub fn password_encryption(&self) -> Result<String, PgError> {
+        self.query_scalar("SHOW password_encryption;", &[])
+    }
+
+    /// Hash `password` for `user` on the client side, to be sent in e.g. `ALTER ROLE ...
+    /// PASSWORD` without revealing it; `algorithm` is `scram-sha-256` or `md5`, by default
+    /// the server's [`PgConn::password_encryption`].
+    /// See also [PQencryptPasswordConn](https://www.postgresql.org/docs/current/libpq-misc.html#LIBPQ-PQENCRYPTPASSWORDCONN).
+    pub fn encrypt_password(
+        &self,
+        password: &str,
+        user: &str,
+        algorithm: Option<&str>,
+    ) -> Result<String, PgError> {
+        let c_password = CString::new(password)?;
+        let c_user = CString::new(user)?;
+        let c_algorithm = algorithm.map(CString::new).transpose()?;
+        unsafe {
+            let encrypted = PQencryptPasswordConn(
+                self.conn,
+                c_password.as_ptr(),
+                c_user.as_ptr(),
+                c_algorithm
+                    .as_ref()
+                    .map_or(std::ptr::null(), |a| a.as_ptr()),
+            );
+            match PqMem::new(encrypted) {
+                Some(encrypted) => Ok(encrypted.to_string_lossy()),
+                None => Err(PgError::Connection(self.error_message())),
+            }
+        }
+    }
+
+    /// Set the session's `statement_timeout` in whole milliseconds, rounding a non-zero
+    /// `timeout` up so that it is not mistaken for `0` (no timeout), or reset it to the
+    /// server default with `None`.
+    pub fn set_statement_timeout(
+        &mut self,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<(), PgError> {
+        let value = match timeout {
+            Some(timeout) => timeout.as_nanos().div_ceil(1_000_000).to_string(),
+            None => "DEFAULT".to_string(),
+        };
+        self.exec(&format!("SET statement_timeout = {};", value))?
+            .check()?;
+        Ok(())
+    }
+
+    /// Set the session's `search_path` to the given schemas, in order, each escaped as an
+    /// identifier; an empty list leaves no schema in the path.
+    pub fn set_search_path(&mut self, schemas: &[&str]) -> Result<(), PgError> {
+        let schemas = schemas
+            .iter()
+            .map(|schema| self.escape_identifier(schema))
+            .collect::<Result<Vec<_>, _>>()?;
+        let value = if schemas.is_empty() {
+            "''".to_string()
+        } else {
+            schemas.join(", ")
+        };
+        self.exec(&format!("SET search_path = {};", value))?
+            .check()?;
+        Ok(())
+    }
+
+    /// Switch the session's current role with `SET ROLE`, the name escaped as an identifier.
+    pub fn set_role(&mut self, role: &str) -> Result<(), PgError> {
+        let query = format!("SET ROLE {};", self.escape_identifier(role)?);
+        self.exec(&query)?.check()?;
+        Ok(())
+    }
+
+    /// Go back to the session's original role with `RESET ROLE`.
+    pub fn reset_role(&mut self) -> Result<(), PgError> {
+        self.exec("RESET ROLE;")?.check()?;
+        Ok(())
+    }
+
+    /// Change the `application_name` shown in `pg_stat_activity` for this session.
+    ///
+    /// The name is bound as a parameter of `set_config`, since `SET` doesn't accept any.
+    pub fn set_application_name(&mut self, name: &str) -> Result<(), PgError> {
+        self.exec_params(
+            "SELECT set_config('application_name', $1, false);",
+            &[&name],
+        )?
+        .check()?;
+        Ok(())
+    }
+
+    /// Execute a command with the given parameters, referred to as `$1`, `$2`, ... in the
+    /// query. Each parameter is sent in text format with the type given by its [`ToSql`]
+    /// implementation; strings and `None` leave the type to be inferred by the server.
+    /// See also [PQexecParams](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQEXECPARAMS).
+    pub fn exec_params(&self, query: &str, params: &[&dyn ToSql]) -> Result<PgResult, PgError> {
+        let c_query = CString::new(query)?;
+        let params = TextParams::new(params)?;
+        let values = params.values();
+
+        unsafe {
+            let res = PQexecParams(
+                self.conn,
+                c_query.as_ptr(),
+                values.len() as i32,
+                params.types.as_ptr(),
+                values.as_ptr(),
+                std::ptr::null(),
+                std::ptr::null(),
+                0,
+            );
+            Ok(PgResult { res })
+        }
+    }
+
+    /// Create the prepared statement `name`, to be run with [`PgConn::exec_prepared`].
+    /// Parameter types missing from `param_types`, or given as [`INVALID_OID`], are inferred
+    /// by the server.
+    /// See also [PQprepare](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQPREPARE).
+    pub fn prepare(
+        &self,
+        name: &str,
+        query: &str,
+        param_types: &[Oid],
+    ) -> Result<PgResult, PgError> {
+        let c_name = CString::new(name)?;
+        let c_query = CString::new(query)?;
+
+        unsafe {
+            let res = PQprepare(
+                self.conn,
+                c_name.as_ptr(),
+                c_query.as_ptr(),
+                param_types.len() as i32,
+                param_types.as_ptr(),
+            );
+            Ok(PgResult { res })
+        }
+    }
+
+    /// Execute the prepared statement `name` with the given parameters, sent in text format;
+    /// their types are those the statement was prepared with.
+    /// See also [PQexecPrepared](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQEXECPREPARED).
+    pub fn exec_prepared(&self, name: &str, params: &[&dyn ToSql]) -> Result<PgResult, PgError> {
+        let c_name = CString::new(name)?;
+        let params = TextParams::new(params)?;
+        let values = params.values();
+
+        unsafe {
+            let res = PQexecPrepared(
+                self.conn,
+                c_name.as_ptr(),
+                values.len() as i32,
+                values.as_ptr(),
+                std::ptr::null(),
+                std::ptr::null(),
+                0,
+            );
+            Ok(PgResult { res })
+        }
+    }
+
+    /// Execute a statement with the given parameters and return the number of affected rows,
+    /// as reported by [`PgResult::cmd_tuples`].
+    pub fn execute(&self, sql: &str, params: &[&dyn ToSql]) -> Result<u64, PgError> {
+        Ok(self.exec_params(sql, params)?.check()?.cmd_tuples())
+    }
+
+    /// Execute a query expected to return exactly one row, mapped via [`FromRow`].
+    ///
+    /// Any other number of rows is reported as [`PgError::UnexpectedRowCount`].
+    pub fn query_one<T: FromRow>(&self, sql: &str, params: &[&dyn ToSql]) -> Result<T, PgError> {
+        self.query_opt(sql, params)?
+            .ok_or(PgError::UnexpectedRowCount(0))
+    }
+
+    /// Execute a query expected to return at most one row, mapped via [`FromRow`], or
+    /// `None` if it returns no rows.
+    ///
+    /// More than one row is reported as [`PgError::UnexpectedRowCount`].
+    pub fn query_opt<T: FromRow>(
+        &self,
+        sql: &str,
+        params: &[&dyn ToSql],
+    ) -> Result<Option<T>, PgError> {
+        let res = self.exec_params(sql, params)?.check()?;
+        match res.ntuples() {
+            0 => Ok(None),
+            1 => T::from_row(&res.row(0)).map(Some),
+            n => Err(PgError::UnexpectedRowCount(n)),
+        }
+    }
+
+    /// Execute a query and decode the first column of its first row via [`FromSql`].
+    ///
+    /// A query returning no rows is reported as [`PgError::UnexpectedRowCount`], while
+    /// further rows and columns are ignored.
+    pub fn query_scalar<T: FromSql>(&self, sql: &str, params: &[&dyn ToSql]) -> Result<T, PgError> {
+        let res = self.exec_params(sql, params)?.check()?;
+        if res.ntuples() == 0 {
+            return Err(PgError::UnexpectedRowCount(0));
+        }
+        res.get(0, 0)
+    }
+
+    /// The length in bytes of the first character of `s` under the client encoding, or 0 if
+    /// `s` is empty.
+    /// See also [PQmblenBounded](https://www.postgresql.org/docs/current/libpq-misc.html#LIBPQ-PQMBLENBOUNDED).
+    pub fn mblen(&self, s: &str) -> usize {
+        let buf = nul_terminated(s);
+        unsafe {
+            PQmblenBounded(buf.as_ptr() as *const c_char, PQclientEncoding(self.conn)) as usize
+        }
+    }
+
+    /// The display width of `s` under the client encoding, summing that of each character;
+    /// control characters count as zero.
+    /// See also [PQdsplen](https://www.postgresql.org/docs/current/libpq-misc.html#LIBPQ-PQDSPLEN).
+    pub fn dsplen(&self, s: &str) -> usize {
+        let buf = nul_terminated(s);
+        let encoding = unsafe { PQclientEncoding(self.conn) };
+        let mut width = 0;
+        let mut offset = 0;
+        while offset < s.len() {
+            let ptr = buf[offset..].as_ptr() as *const c_char;
+            width += unsafe { PQdsplen(ptr, encoding) }.max(0) as usize;
+            offset += unsafe { PQmblenBounded(ptr, encoding) }.max(1) as usize;
+        }
+        width
+    }
+
+    /// Send a notification on the given channel, with an optional payload.
+    ///
+    /// Both are bound as parameters of `pg_notify`, so they need no escaping; note that the
+    /// channel name is therefore taken verbatim, without folding to lower case.
+    pub fn notify(&mut self, channel: &str, payload: Option<&str>) -> Result<PgResult, PgError> {
+        self.exec_params("SELECT pg_notify($1, $2);", &[&channel, &payload])
+    }
+
+    /// Listen on the given channel, whose name is escaped as an identifier, so it is taken
+    /// verbatim just like in [`PgConn::notify`].
+    pub fn listen(&mut self, channel: &str) -> Result<PgResult, PgError> {
+        let query = format!("LISTEN {};", self.escape_identifier(channel)?);
+        let res = self.exec(&query)?;
+        if res.status() == ExecStatusType_PGRES_COMMAND_OK {
+            self.listening.insert(channel.to_string());
+        }
+        Ok(res)
+    }
+
+    /// Escape a string for use as an SQL identifier, quoting it with double quotes.
+    ///
+    /// Unlike unquoted identifiers, the result is not folded to lower case.
+    /// See also [PQescapeIdentifier](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQESCAPEIDENTIFIER).
+    pub fn escape_identifier(&self, s: &str) -> Result<String, PgError> {
+        unsafe {
+            let escaped = PQescapeIdentifier(self.conn, s.as_ptr() as *const c_char, s.len());
+            match PqMem::new(escaped) {
+                Some(escaped) => Ok(escaped.to_string_lossy()),
+                None => Err(PgError::Connection(self.error_message())),
+            }
+        }
+    }
+
+    /// Listen on each of the given channels, whose names are escaped as identifiers
+    /// (so they are case sensitive).
+    pub fn listen_many(&mut self, channels: &[&str]) -> Result<(), PgError> {
+        for channel in channels {
+            let query = format!("LISTEN {};", self.escape_identifier(channel)?);
+            self.exec(&query)?.check()?;
+            self.listening.insert(channel.to_string());
+        }
+        Ok(())
+    }
+
+    /// Stop listening on the given channel, whose name is escaped as an identifier.
+    pub fn unlisten(&mut self, channel: &str) -> Result<(), PgError> {
+        let query = format!("UNLISTEN {};", self.escape_identifier(channel)?);
+        self.exec(&query)?.check()?;
+        self.listening.remove(channel);
+        Ok(())
+    }
+
+    /// Stop listening on all channels.
+    pub fn unlisten_all(&mut self) -> Result<(), PgError> {
+        self.exec("UNLISTEN *;")?.check()?;
+        self.listening.clear();
+        Ok(())
+    }
+
+    /// The channels this connection subscribed to with [`PgConn::listen`] or
+    /// [`PgConn::listen_many`] and didn't unsubscribe from since.
+    pub fn listening(&self) -> impl Iterator<Item = &str> {
+        self.listening.iter().map(String::as_str)
+    }
+
+    /// Reset the connection, reconnecting with the same parameters, then listen again on
+    /// every channel in [`PgConn::listening`], since subscriptions don't survive a new session.
+    ///
+    /// Channels subscribed to by executing `LISTEN` directly are not restored.
+    /// See also [PQreset](https://www.postgresql.org/docs/current/libpq-connect.html#LIBPQ-PQRESET).
+    pub fn reconnect(&mut self) -> Result<(), PgError> {
+        unsafe { PQreset(self.conn) };
+        // Prepared statements don't survive a new session either.
+        self.statement_cache.forget();
+        if !self.is_ok() {
+            return Err(PgError::Connection(self.error_message()));
+        }
+
+        let channels: Vec<String> = self.listening.iter().cloned().collect();
+        for channel in channels {
+            let query = format!("LISTEN {};", self.escape_identifier(&channel)?);
+            self.exec(&query)?.check()?;
+        }
+        Ok(())
+    }
+
+    /// Close the connection now rather than when it goes out of scope, releasing its socket
+    /// and server session, as dropping it does.
+    /// See also [PQfinish](https://www.postgresql.org/docs/current/libpq-connect.html#LIBPQ-PQFINISH).
+    pub fn close(self) {
+        drop(self);
+    }
+
+    ///
+    /// A callback function to receive notices from the server.
+    /// https://stackoverflow.com/questions/24191249/working-with-c-void-in-an-ffi
+    /// https://adventures.michaelfbryan.com/posts/rust-closures-in-ffi/
+    extern "C" fn ffi_notice_processor<F>(arg: *mut c_void, data: *const c_char)
+    where
+        F: FnMut(String),
+    {
+        unsafe {
+            let s = std::ffi::CStr::from_ptr(data)
+                .to_string_lossy()
+                .into_owned();
+
+            let f = &mut *(arg as *mut F);
+
+            f(s);
+        }
+    }
+
+    /// Sets a notice processor function to receive notices from the server as formatted text.
+    ///
+    /// The closure is owned by the connection, so it stays alive for as long as libpq may call
+    /// it. The processor it replaces is returned, to be reinstated with
+    /// [`PgConn::restore_notice_processor`]; dropping that handle instead drops the closure
+    /// it holds, if any.
+    /// https://www.postgresql.org/docs/current/libpq-notice-processing.html
+    pub fn set_notice_processor<F>(&mut self, proc: F) -> PreviousNoticeProcessor
+    where
+        F: FnMut(String) + Send + 'static,
+    {
+        let mut b = Box::new(proc);
+        let a = b.as_mut() as *mut F as *mut c_void;
+        let func =
+            unsafe { PQsetNoticeProcessor(self.conn, Some(Self::ffi_notice_processor::<F>), a) };
+        if self.notice_processor.is_none() {
+            self.default_notice_processor = func;
+        }
+        PreviousNoticeProcessor {
+            func,
+            closure: self.notice_processor.replace(b),
+        }
+    }
+
+    /// Reinstall a notice processor returned by [`PgConn::set_notice_processor`], dropping
+    /// the closure currently installed, if any.
+    pub fn restore_notice_processor(&mut self, mut prev: PreviousNoticeProcessor) {
+        unsafe {
+            PQsetNoticeProcessor(self.conn, prev.func, closure_ptr(&mut prev.closure));
+        }
+        self.notice_processor = prev.closure;
+    }
+
+    /// Reinstall libpq's default notice processor, which prints notices to `stderr`,
+    /// and drop the closure installed by [`PgConn::set_notice_processor`], if any.
+    pub fn reset_notice_processor(&mut self) {
+        if self.notice_processor.is_some() {
+            unsafe {
+                PQsetNoticeProcessor(self.conn, self.default_notice_processor, null_mut());
+            }
+            self.notice_processor = None;
         }
     }
 
     extern "C" fn ffi_notice_receiver<F>(arg: *mut c_void, data: *const PGresult)
     where
-        F: FnMut(PgResult),
+        F: FnMut(&PgResult),
     {
         unsafe {
-            let s = PgResult {
+            // libpq clears the result once the receiver returns, so it must not be dropped here.
+            let s = std::mem::ManuallyDrop::new(PgResult {
                 res: data as *mut PGresult,
-            };
+            });
 
             let f = &mut *(arg as *mut F);
 
-            f(s);
+            f(&s);
         }
     }
 
+    /// Install a notice processor emitting each notice through the `log` crate, with target
+    /// `libpq`, at the level matching its severity: `WARNING` at [`log::Level::Warn`],
+    /// `NOTICE`, `INFO` and `LOG` at [`log::Level::Info`], `DEBUG` at [`log::Level::Debug`].
+    ///
+    /// The severity prefix is stripped from the message; a notice without a known prefix,
+    /// e.g. in a translated message, is logged as it is at [`log::Level::Info`]. Returns the
+    /// processor it replaces, as [`PgConn::set_notice_processor`] does.
+    #[cfg(feature = "log")]
+    pub fn log_notices(&mut self) -> PreviousNoticeProcessor {
+        self.set_notice_processor(|notice| {
+            let (level, message) = notice_level(&notice);
+            log::log!(target: "libpq", level, "{}", message.trim_end());
+        })
+    }
+
     /// Sets a notice receiver function to receive notices from the server.
     /// Notices are sent to the receiver after command execution is completed.
+    ///
+    /// The receiver gets each notice as a result, whose fields are available from
+    /// [`PgResult::notice`].
+    ///
+    /// As for [`PgConn::set_notice_processor`], the closure is owned by the connection and
+    /// the receiver it replaces is returned, see [`PgConn::restore_notice_receiver`].
     /// https://www.postgresql.org/docs/current/libpq-notice-processing.html
-    pub fn set_notice_receiver<F>(&mut self, proc: F) -> Box<F>
+    pub fn set_notice_receiver<F>(&mut self, proc: F) -> PreviousNoticeReceiver
     where
-        F: FnMut(PgResult),
+        F: FnMut(&PgResult) + Send + 'static,
     {
+        let mut b = Box::new(proc);
+        let a = b.as_mut() as *mut F as *mut c_void;
+        let func =
+            unsafe { PQsetNoticeReceiver(self.conn, Some(Self::ffi_notice_receiver::<F>), a) };
+        PreviousNoticeReceiver {
+            func,
+            closure: self.notice_receiver.replace(b),
+        }
+    }
+
+    /// Reinstall a notice receiver returned by [`PgConn::set_notice_receiver`], dropping
+    /// the closure currently installed, if any.
+    pub fn restore_notice_receiver(&mut self, mut prev: PreviousNoticeReceiver) {
         unsafe {
-            let mut b = Box::new(proc);
-            let a = b.as_mut() as *mut F as *mut c_void;
-            PQsetNoticeReceiver(self.conn, Some(Self::ffi_notice_receiver::<F>), a);
-            b
+            PQsetNoticeReceiver(self.conn, prev.func, closure_ptr(&mut prev.closure));
         }
+        self.notice_receiver = prev.closure;
+    }
+
+    /// Wait for notifications, passing each one to `proc` along with its index, and collect
+    /// the values it returns through `ControlFlow::Continue(Some(...))`.
+    ///
+    /// The loop ends with `Ok` when `proc` returns `ControlFlow::Break` or when no data arrives
+    /// within `timeout_sec`, and with `Err` if polling the socket or reading from the
+    /// connection fails, e.g. because the server closed it.
+    pub fn listen_loop<F, T>(
+        &mut self,
+        timeout_sec: Option<f64>,
+        proc: F,
+    ) -> Result<Vec<T>, PgError>
+    where
+        F: Fn(usize, PgNotify) -> ControlFlow<(), Option<T>>,
+    {
+        self.run_listen_loop(timeout_sec, None, proc)
+    }
+
+    /// Like [`PgConn::listen_loop`], but keeps waiting until `stop` is set, typically from
+    /// another thread, instead of ending when no data arrives.
+    ///
+    /// The flag is checked before every notification and every `poll_interval_sec` seconds
+    /// while idle, which bounds how long stopping can take.
+    pub fn listen_loop_until<F, T>(
+        &mut self,
+        poll_interval_sec: f64,
+        stop: &AtomicBool,
+        proc: F,
+    ) -> Result<Vec<T>, PgError>
+    where
+        F: Fn(usize, PgNotify) -> ControlFlow<(), Option<T>>,
+    {
+        self.run_listen_loop(Some(poll_interval_sec), Some(stop), proc)
     }
 
-    pub fn listen_loop<F, T>(&mut self, timeout_sec: Option<f64>, proc: F) -> Vec<T>
+    fn run_listen_loop<F, T>(
+        &mut self,
+        timeout_sec: Option<f64>,
+        stop: Option<&AtomicBool>,
+        proc: F,
+    ) -> Result<Vec<T>, PgError>
     where
         F: Fn(usize, PgNotify) -> ControlFlow<(), Option<T>>,
     {
+        let stopped = || stop.is_some_and(|s| s.load(Ordering::Acquire));
+
         let mut recvs = Vec::new();
 
         let mut count = 0;
 
-        loop {
+        'outer: while !stopped() {
             match self.socket().poll(true, false, timeout_sec) {
                 Ok(()) => {
-                    self.consume_input().expect("Failed to consume input.");
+                    self.consume_input().map_err(PgError::Connection)?;
 
                     while let Some(notify) = self.notifies() {
                         match proc(count, notify) {
                             ControlFlow::Continue(Some(p)) => recvs.push(p),
                             ControlFlow::Break(()) => {
-                                break;
+                                break 'outer;
                             }
                             _ => {} // Do nothing
                         }
-                        self.consume_input().expect("Failed to consume input.");
+                        self.consume_input().map_err(PgError::Connection)?;
                         count += 1;
+
+                        if stopped() {
+                            break 'outer;
+                        }
                     }
                 }
-                Err(_e) => break,
+                Err(PgSocketPollResult::Timeout) if stop.is_some() => continue,
+                Err(PgSocketPollResult::Timeout) => break,
+                Err(PgSocketPollResult::Error(e)) => return Err(PgError::Connection(e)),
             }
         }
 
-        recvs
+        Ok(recvs)
     }
 }
 
-impl PgResult {
-    pub fn status(&self) -> ExecStatusType {
-        unsafe { PQresultStatus(self.res) }
+/// Builds a connection from separate keyword/value pairs, so values need no quoting or
+/// escaping as in a conninfo string. Parameters that are not set fall back to the
+/// [environment variables](https://www.postgresql.org/docs/current/libpq-envars.html).
+///
+/// See the [official doc](https://www.postgresql.org/docs/current/libpq-connect.html#LIBPQ-PARAMKEYWORDS)
+/// for the available keywords.
+#[derive(Debug, Clone, Default)]
+pub struct PgConnBuilder {
+    params: Vec<(String, String)>,
+}
+
+impl PgConnBuilder {
+    pub fn new() -> PgConnBuilder {
+        PgConnBuilder::default()
     }
 
-    pub fn cmd_status(&mut self) -> String {
-        unsafe {
-            let s = PQcmdStatus(self.res);
-            std::ffi::CStr::from_ptr(s).to_string_lossy().into_owned()
-        }
+    /// Set an arbitrary connection parameter, replacing any previous value for `key`.
+    pub fn param(mut self, key: &str, value: &str) -> PgConnBuilder {
+        self.params.retain(|(k, _)| k != key);
+        self.params.push((key.to_string(), value.to_string()));
+        self
     }
 
-    pub fn error_message(&self) -> String {
-        unsafe {
-            let s = PQresultErrorMessage(self.res);
-            std::ffi::CStr::from_ptr(s).to_string_lossy().into_owned()
-        }
+    pub fn host(self, host: &str) -> PgConnBuilder {
+        self.param("host", host)
     }
 
-    pub fn error_field(&self, field_code: u8) -> Option<String> {
-        unsafe {
-            let s = PQresultErrorField(self.res, field_code.into());
-            if s.is_null() {
-                None
-            } else {
-                Some(std::ffi::CStr::from_ptr(s).to_string_lossy().into_owned())
-            }
-        }
+    pub fn port(self, port: u16) -> PgConnBuilder {
+        self.param("port", &port.to_string())
     }
 
-    pub fn get_value<T>(&self, row: i32, col: i32) -> Option<T>
-    where
+    pub fn dbname(self, dbname: &str) -> PgConnBuilder {
+        self.param("dbname", dbname)
+    }
+
+    pub fn user(self, user: &str) -> PgConnBuilder {
+        self.param("user", user)
+    }
+
+    pub fn password(self, password: &str) -> PgConnBuilder {
+        self.param("password", password)
+    }
+
+    pub fn application_name(self, name: &str) -> PgConnBuilder {
+        self.param("application_name", name)
+    }
+
+    /// Maximum time to wait while connecting, in seconds.
+    pub fn connect_timeout(self, seconds: u32) -> PgConnBuilder {
+        self.param("connect_timeout", &seconds.to_string())
+    }
+
+    /// One of `disable`, `allow`, `prefer`, `require`, `verify-ca` or `verify-full`.
+    pub fn sslmode(self, mode: &str) -> PgConnBuilder {
+        self.param("sslmode", mode)
+    }
+
+    /// A service defined in the connection service file, `pg_service.conf`, whose parameters
+    /// are used for the ones not set otherwise.
+    pub fn service(self, name: &str) -> PgConnBuilder {
+        self.param("service", name)
+    }
+
+    /// The password file to look up the password in, instead of `~/.pgpass`.
+    pub fn passfile(self, path: &str) -> PgConnBuilder {
+        self.param("passfile", path)
+    }
+
+    /// Whether to use TCP keepalives, so that a dead peer is detected on an idle connection,
+    /// e.g. one waiting for notifications; they are on by default.
+    pub fn keepalives(self, enabled: bool) -> PgConnBuilder {
+        self.param("keepalives", if enabled { "1" } else { "0" })
+    }
+
+    /// The idle time after which a keepalive is sent, rounded up to whole seconds; zero
+    /// leaves it to the system default.
+    pub fn keepalives_idle(self, idle: std::time::Duration) -> PgConnBuilder {
+        self.param("keepalives_idle", &whole_seconds(idle))
+    }
+
+    /// The time after which an unanswered keepalive is sent again, rounded up to whole
+    /// seconds; zero leaves it to the system default.
+    pub fn keepalives_interval(self, interval: std::time::Duration) -> PgConnBuilder {
+        self.param("keepalives_interval", &whole_seconds(interval))
+    }
+
+    /// The number of unanswered keepalives after which the connection is considered dead.
+    pub fn keepalives_count(self, count: u32) -> PgConnBuilder {
+        self.param("keepalives_count", &count.to_string())
+    }
+
+    /// Connect with the collected parameters; a connection whose status is not
+    /// `CONNECTION_OK` is reported as [`PgError::Connection`].
+    /// See also [PQconnectdbParams](https://www.postgresql.org/docs/current/libpq-connect.html#LIBPQ-PQCONNECTDBPARAMS).
+    pub fn connect(&self) -> Result<PgConn, PgError> {
+        let keys = self
+            .params
+            .iter()
+            .map(|(k, _)| CString::new(k.as_str()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let values = self
+            .params
+            .iter()
+            .map(|(_, v)| CString::new(v.as_str()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut key_ptrs: Vec<*const c_char> = keys.iter().map(|k| k.as_ptr()).collect();
+        let mut value_ptrs: Vec<*const c_char> = values.iter().map(|v| v.as_ptr()).collect();
+        key_ptrs.push(std::ptr::null());
+        value_ptrs.push(std::ptr::null());
+
+        let conn = PgConn::from_raw(unsafe {
+            PQconnectdbParams(key_ptrs.as_ptr(), value_ptrs.as_ptr(), 0)
+        });
+        if conn.is_ok() {
+            Ok(conn)
+        } else {
+            Err(PgError::Connection(conn.error_message()))
+        }
+    }
+}
+
+impl PgResult {
+    pub fn status(&self) -> ExecStatusType {
+        unsafe { PQresultStatus(self.res) }
+    }
+
+    /// Like [`PgResult::status`], as an [`ExecStatus`].
+    pub fn status_enum(&self) -> ExecStatus {
+        self.status().into()
+    }
+
+    /// The name of the status, e.g. `"PGRES_TUPLES_OK"`.
+    /// See also [PQresStatus](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQRESSTATUS).
+    pub fn status_str(&self) -> String {
+        unsafe {
+            std::ffi::CStr::from_ptr(PQresStatus(self.status()))
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
+
+    /// Whether the status is `PGRES_FATAL_ERROR`, `PGRES_NONFATAL_ERROR` or
+    /// `PGRES_BAD_RESPONSE`.
+    pub fn is_error(&self) -> bool {
+        [
+            ExecStatusType_PGRES_FATAL_ERROR,
+            ExecStatusType_PGRES_NONFATAL_ERROR,
+            ExecStatusType_PGRES_BAD_RESPONSE,
+        ]
+        .contains(&self.status())
+    }
+
+    /// Whether the status is one that [`PgResult::check`] accepts: `PGRES_COMMAND_OK`,
+    /// `PGRES_TUPLES_OK`, `PGRES_SINGLE_TUPLE` or `PGRES_TUPLES_CHUNK`.
+    pub fn is_success(&self) -> bool {
+        [
+            ExecStatusType_PGRES_COMMAND_OK,
+            ExecStatusType_PGRES_TUPLES_OK,
+            ExecStatusType_PGRES_SINGLE_TUPLE,
+            ExecStatusType_PGRES_TUPLES_CHUNK,
+        ]
+        .contains(&self.status())
+    }
+
+    /// Whether the status is `PGRES_COPY_IN`, `PGRES_COPY_OUT` or `PGRES_COPY_BOTH`: the
+    /// command started a COPY, whose data is now to be transferred.
+    pub fn is_copy(&self) -> bool {
+        [
+            ExecStatusType_PGRES_COPY_IN,
+            ExecStatusType_PGRES_COPY_OUT,
+            ExecStatusType_PGRES_COPY_BOTH,
+        ]
+        .contains(&self.status())
+    }
+
+    /// Whether the result is the `PGRES_PIPELINE_SYNC` marking the end of a pipeline
+    /// segment, see [`PgConn::pipeline_sync`].
+    pub fn is_pipeline_sync(&self) -> bool {
+        self.status() == ExecStatusType_PGRES_PIPELINE_SYNC
+    }
+
+    /// Whether the query was skipped, with status `PGRES_PIPELINE_ABORTED`, because an
+    /// earlier query of the same pipeline segment failed.
+    pub fn is_pipeline_aborted(&self) -> bool {
+        self.status() == ExecStatusType_PGRES_PIPELINE_ABORTED
+    }
+
+    /// The command status tag of the SQL command that generated the result, e.g. `SELECT 1`.
+    /// See also [PQcmdStatus](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQCMDSTATUS).
+    pub fn cmd_status(&self) -> String {
+        unsafe {
+            let s = PQcmdStatus(self.res);
+            std::ffi::CStr::from_ptr(s).to_string_lossy().into_owned()
+        }
+    }
+
+    /// The number of rows affected by the SQL command, or 0 for commands that don't report one.
+    /// See also [PQcmdTuples](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQCMDTUPLES).
+    pub fn cmd_tuples(&self) -> u64 {
+        unsafe {
+            let s = PQcmdTuples(self.res);
+            if s.is_null() {
+                0
+            } else {
+                std::ffi::CStr::from_ptr(s)
+                    .to_string_lossy()
+                    .parse()
+                    .unwrap_or(0)
+            }
+        }
+    }
+
+    /// The OID of the inserted row, if the command was an `INSERT` of exactly one row
+    /// into a table that has OIDs.
+    ///
+    /// Since PostgreSQL 12 user tables can no longer be created `WITH OIDS`, so this is
+    /// almost always `None`; use `INSERT ... RETURNING` to get generated keys instead.
+    /// See also [PQoidValue](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQOIDVALUE).
+    pub fn oid_value(&self) -> Option<Oid> {
+        match unsafe { PQoidValue(self.res) } {
+            INVALID_OID => None,
+            oid => Some(oid),
+        }
+    }
+
+    pub fn error_message(&self) -> String {
+        unsafe {
+            let s = PQresultErrorMessage(self.res);
+            std::ffi::CStr::from_ptr(s).to_string_lossy().into_owned()
+        }
+    }
+
+    /// Like [`PgResult::error_message`], but failing on invalid UTF-8 instead of replacing it.
+    pub fn error_message_strict(&self) -> Result<String, Utf8Error> {
+        unsafe { strict_string(PQresultErrorMessage(self.res)) }
+    }
+
+    /// A reformatted version of the error message with the given verbosity and context
+    /// visibility, independent of the connection's own settings.
+    /// See also [PQresultVerboseErrorMessage](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQRESULTVERBOSEERRORMESSAGE).
+    pub fn verbose_error_message(
+        &self,
+        verbosity: Verbosity,
+        context: ContextVisibility,
+    ) -> String {
+        unsafe {
+            let s = PQresultVerboseErrorMessage(self.res, verbosity.into(), context.into());
+            PqMem::new(s).map_or_else(String::new, |s| s.to_string_lossy())
+        }
+    }
+
+    pub fn error_field(&self, field_code: u8) -> Option<String> {
+        unsafe {
+            let s = PQresultErrorField(self.res, field_code.into());
+            if s.is_null() {
+                None
+            } else {
+                Some(std::ffi::CStr::from_ptr(s).to_string_lossy().into_owned())
+            }
+        }
+    }
+
+    pub fn get_value<T>(&self, row: i32, col: i32) -> Option<T>
+    where
         T: std::str::FromStr,
     {
         unsafe {
@@ -358,7 +2202,91 @@ impl PgResult {
         }
     }
 
-    /// Print the result to a file.
+    /// Parse the value at the specified row and column, telling SQL NULL (`Ok(None)`) apart
+    /// from a value that fails to parse (`Err` with the parse error).
+    pub fn try_get_value<T>(&self, row: i32, col: i32) -> Result<Option<T>, T::Err>
+    where
+        T: std::str::FromStr,
+    {
+        if self.is_null(row, col) {
+            Ok(None)
+        } else {
+            self.get_value_raw(row, col).parse().map(Some)
+        }
+    }
+
+    /// Decode the value at the specified row and column via its [`FromSql`] implementation,
+    /// which is given the column type as reported by [`PgResult::field_type`].
+    ///
+    /// SQL NULL is reported as [`PgError::UnexpectedNull`], unless `T` is an `Option`, and a
+    /// row out of range as [`PgError::RowNotFound`].
+    pub fn get<T: FromSql>(&self, row: i32, col: i32) -> Result<T, PgError> {
+        let col = col.column_index(self)?;
+        if !(0..self.ntuples()).contains(&row) {
+            return Err(PgError::RowNotFound(row));
+        }
+
+        if self.is_null(row, col) {
+            return T::from_sql_null().ok_or(PgError::UnexpectedNull { row, col });
+        }
+
+        let value = self.get_value_raw(row, col);
+        T::from_sql(&value, self.field_type(col)).ok_or(PgError::InvalidValue { row, col, value })
+    }
+
+    /// Parse the JSON value at the specified row and column, as with
+    /// [`PgResult::get::<serde_json::Value>`](PgResult::get).
+    ///
+    /// SQL NULL is [`serde_json::Value::Null`], and malformed JSON is reported as
+    /// [`PgError::InvalidValue`].
+    #[cfg(feature = "json")]
+    pub fn get_json(&self, row: i32, col: i32) -> Result<serde_json::Value, PgError> {
+        self.get(row, col)
+    }
+
+    /// Parse the one-dimensional array at the specified row and column, each element with
+    /// [`FromStr`](std::str::FromStr).
+    ///
+    /// Returns `None` for SQL NULL, multi-dimensional arrays, NULL elements or elements that
+    /// fail to parse; see [`PgResult::get_array_opt`] to keep NULL elements.
+    pub fn get_array<T>(&self, row: i32, col: i32) -> Option<Vec<T>>
+    where
+        T: std::str::FromStr,
+    {
+        self.get_array_opt(row, col)?.into_iter().collect()
+    }
+
+    /// Like [`PgResult::get_array`], but NULL elements are kept as `None`.
+    pub fn get_array_opt<T>(&self, row: i32, col: i32) -> Option<Vec<Option<T>>>
+    where
+        T: std::str::FromStr,
+    {
+        if self.is_null(row, col) {
+            return None;
+        }
+        parse_array_literal(&self.get_value_raw(row, col))?
+            .into_iter()
+            .map(|item| match item {
+                Some(s) => s.parse().ok().map(Some),
+                None => Some(None),
+            })
+            .collect()
+    }
+
+    /// Get a boolean value at the specified row and column, accepting Postgres' `t`/`f`
+    /// text output as well as `true`/`false` and `1`/`0`.
+    /// Returns `None` for NULL or any other value.
+    pub fn get_bool(&self, row: i32, col: i32) -> Option<bool> {
+        if self.is_null(row, col) {
+            return None;
+        }
+        bool::from_sql(&self.get_value_raw(row, col), self.field_type(col))
+    }
+
+    /// Print the result to a file, replacing its content.
+    ///
+    /// Fails if `filename` or `fieldsep` contains a NUL byte, or if the file cannot be opened
+    /// or written.
     /// See the [official doc](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQPRINT
     pub fn print(
         &self,
@@ -370,78 +2298,1028 @@ impl PgResult {
         html3: bool,
         expanded: bool,
         pager: bool,
-    ) {
-        unsafe {
-            let sep = CString::new(fieldsep).unwrap();
-
-            let printopt = PQprintOpt {
-                header: header.into(),
-                align: align.into(),
-                fieldSep: sep.as_ptr() as *mut c_char,
-                tableOpt: null_mut(),
-                caption: null_mut(),
-                standard: standard.into(),
-                html3: html3.into(),
-                expanded: expanded.into(),
-                pager: pager.into(),
-                fieldName: null_mut(),
-            };
+    ) -> Result<(), PgError> {
+        let sep = CString::new(fieldsep)?;
+        let c_filename = CString::new(filename)?;
 
-            let fp = fopen(
-                CString::new(filename).unwrap().as_ptr(),
-                CString::new("w").unwrap().as_ptr(),
-            );
+        let printopt = PQprintOpt {
+            header: header.into(),
+            align: align.into(),
+            fieldSep: sep.as_ptr() as *mut c_char,
+            tableOpt: null_mut(),
+            caption: null_mut(),
+            standard: standard.into(),
+            html3: html3.into(),
+            expanded: expanded.into(),
+            pager: pager.into(),
+            fieldName: null_mut(),
+        };
+
+        unsafe {
+            let fp = fopen(c_filename.as_ptr(), c"w".as_ptr());
+            if fp.is_null() {
+                return Err(std::io::Error::last_os_error().into());
+            }
 
             PQprint(fp, self.res, &printopt);
 
-            assert_eq!(fflush(fp), 0);
-            assert_eq!(fclose(fp), 0);
+            // Close the file even if flushing it failed, reporting the first error.
+            let flushed = (fflush(fp) == 0)
+                .then_some(())
+                .ok_or_else(std::io::Error::last_os_error);
+            let closed = (fclose(fp) == 0)
+                .then_some(())
+                .ok_or_else(std::io::Error::last_os_error);
+            Ok(flushed.and(closed)?)
         }
     }
 
     /// Get the value at the specified row and column.
+    ///
+    /// The whole value is read, using its [length](PgResult::get_length), so NUL bytes
+    /// don't truncate it; invalid UTF-8 is replaced.
     /// See also [PQgetvalue](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQGETVALUE).
     pub fn get_value_raw(&self, row: i32, col: i32) -> String {
+        String::from_utf8_lossy(self.get_bytes(row, col)).into_owned()
+    }
+
+    /// The length in bytes of the value at the specified row and column, 0 for NULL.
+    /// See also [PQgetlength](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQGETLENGTH).
+    pub fn get_length(&self, row: i32, col: i32) -> i32 {
+        unsafe { PQgetlength(self.res, row, col) }
+    }
+
+    /// The bytes of the value at the specified row and column as received, e.g. a value in
+    /// binary format; empty for NULL.
+    pub fn get_bytes(&self, row: i32, col: i32) -> &[u8] {
         unsafe {
             let s = PQgetvalue(self.res, row, col);
             if s.is_null() {
-                "".to_string()
+                &[]
             } else {
-                std::ffi::CStr::from_ptr(s).to_string_lossy().into_owned()
+                std::slice::from_raw_parts(s as *const u8, self.get_length(row, col) as usize)
+            }
+        }
+    }
+
+    /// Like [`PgResult::get_value_raw`], but failing on invalid UTF-8 instead of replacing it.
+    pub fn get_value_raw_strict(&self, row: i32, col: i32) -> Result<String, Utf8Error> {
+        std::str::from_utf8(self.get_bytes(row, col)).map(str::to_string)
+    }
+
+    /// The number of bytes allocated for the result.
+    /// See also [PQresultMemorySize](https://www.postgresql.org/docs/current/libpq-misc.html#LIBPQ-PQRESULTMEMORYSIZE).
+    pub fn memory_size(&self) -> usize {
+        unsafe { PQresultMemorySize(self.res) }
+    }
+
+    /// The number of rows (tuples) in the result.
+    /// See also [PQntuples](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQNTUPLES).
+    pub fn ntuples(&self) -> i32 {
+        unsafe { PQntuples(self.res) }
+    }
+
+    /// The number of columns (fields) in each row of the result.
+    /// See also [PQnfields](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQNFIELDS).
+    pub fn nfields(&self) -> i32 {
+        unsafe { PQnfields(self.res) }
+    }
+
+    /// The name of the given column, or `None` if the column number is out of range.
+    /// See also [PQfname](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQFNAME).
+    pub fn field_name(&self, col: i32) -> Option<String> {
+        unsafe {
+            let s = PQfname(self.res, col);
+            if s.is_null() {
+                None
+            } else {
+                Some(std::ffi::CStr::from_ptr(s).to_string_lossy().into_owned())
             }
         }
     }
+
+    /// The type OID of the given column; see [`type_name`] for well-known ones.
+    /// See also [PQftype](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQFTYPE).
+    pub fn field_type(&self, col: i32) -> Oid {
+        unsafe { PQftype(self.res, col) }
+    }
+
+    /// The type modifier of the given column, e.g. the declared length of a `varchar(n)`
+    /// plus a 4-byte header, or `-1` when the type has no modifier.
+    /// See also [PQfmod](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQFMOD).
+    pub fn field_modifier(&self, col: i32) -> i32 {
+        unsafe { PQfmod(self.res, col) }
+    }
+
+    /// The size in bytes of the server's internal representation of the given column's
+    /// type, or a negative value for variable-length types.
+    /// See also [PQfsize](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQFSIZE).
+    pub fn field_size(&self, col: i32) -> i32 {
+        unsafe { PQfsize(self.res, col) }
+    }
+
+    /// The OID of the table the given column was fetched from, or [`INVALID_OID`] if it is
+    /// not a simple reference to a table column (e.g. a computed expression).
+    /// See also [PQftable](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQFTABLE).
+    pub fn field_table(&self, col: i32) -> Oid {
+        unsafe { PQftable(self.res, col) }
+    }
+
+    /// The column number (starting at 1) within its table of the given column, or `0` if it
+    /// is not a simple reference to a table column.
+    /// See also [PQftablecol](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQFTABLECOL).
+    pub fn field_table_col(&self, col: i32) -> i32 {
+        unsafe { PQftablecol(self.res, col) }
+    }
+
+    /// The format of the given column: `0` for text, `1` for binary.
+    /// See also [PQfformat](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQFFORMAT).
+    pub fn field_format(&self, col: i32) -> i32 {
+        unsafe { PQfformat(self.res, col) }
+    }
+
+    /// The metadata of every column, as returned by the `field_*` methods.
+    pub fn columns(&self) -> Vec<ColumnInfo> {
+        (0..self.nfields())
+            .map(|col| ColumnInfo {
+                name: self.field_name(col).unwrap_or_default(),
+                type_oid: self.field_type(col),
+                modifier: self.field_modifier(col),
+                size: self.field_size(col),
+                format: self.field_format(col),
+                table_oid: self.field_table(col),
+                table_col: self.field_table_col(col),
+            })
+            .collect()
+    }
+
+    /// The column number associated with the given column name, if any.
+    ///
+    /// As with SQL, unquoted names are folded to lower case, so use `"\"Name\""`
+    /// to look up a mixed-case column.
+    /// See also [PQfnumber](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQFNUMBER).
+    pub fn field_number(&self, name: &str) -> Option<i32> {
+        let c_name = CString::new(name).ok()?;
+        match unsafe { PQfnumber(self.res, c_name.as_ptr()) } {
+            -1 => None,
+            n => Some(n),
+        }
+    }
+
+    /// Turn a failed result into an error, so that `exec(...)?.check()?` can be used in place
+    /// of comparing [`PgResult::status`] against the `ExecStatusType_*` constants.
+    ///
+    /// `PGRES_COMMAND_OK`, `PGRES_TUPLES_OK`, `PGRES_SINGLE_TUPLE` and `PGRES_TUPLES_CHUNK`
+    /// are successes. The COPY statuses, which mean the command succeeded and its data is to
+    /// be transferred, become a [`PgError::CopyInProgress`]; any other status, notably
+    /// `PGRES_FATAL_ERROR`, `PGRES_BAD_RESPONSE` and `PGRES_EMPTY_QUERY`, becomes a
+    /// [`PgError::Query`].
+    pub fn check(self) -> Result<PgResult, PgError> {
+        let status = self.status();
+        if self.is_copy() {
+            return Err(PgError::CopyInProgress(status));
+        }
+
+        if self.is_success() {
+            Ok(self)
+        } else {
+            Err(PgError::Query {
+                status,
+                message: self.error_message(),
+                sqlstate: self.sqlstate(),
+            })
+        }
+    }
+
+    /// The SQLSTATE code of the error, e.g. `"23505"` for a unique violation.
+    pub fn sqlstate(&self) -> Option<String> {
+        self.error_field(PG_DIAG_SQLSTATE)
+    }
+
+    /// The fields of a notice, for the results passed to a
+    /// [notice receiver](PgConn::set_notice_receiver).
+    ///
+    /// The severity is the untranslated one, e.g. `WARNING`, so that it can be matched on
+    /// whatever the server's `lc_messages`.
+    pub fn notice(&self) -> NoticeInfo {
+        NoticeInfo {
+            severity: self
+                .error_field(PG_DIAG_SEVERITY_NONLOCALIZED)
+                .or_else(|| self.error_field(PG_DIAG_SEVERITY))
+                .unwrap_or_default(),
+            message: self
+                .error_field(PG_DIAG_MESSAGE_PRIMARY)
+                .unwrap_or_default(),
+            detail: self.error_field(PG_DIAG_MESSAGE_DETAIL),
+            hint: self.error_field(PG_DIAG_MESSAGE_HINT),
+        }
+    }
+
+    /// The most commonly needed error fields, fetched in one call.
+    pub fn diagnostics(&self) -> Diagnostics {
+        Diagnostics {
+            severity: self.error_field(PG_DIAG_SEVERITY),
+            sqlstate: self.sqlstate(),
+            message_primary: self.error_field(PG_DIAG_MESSAGE_PRIMARY),
+            detail: self.error_field(PG_DIAG_MESSAGE_DETAIL),
+            hint: self.error_field(PG_DIAG_MESSAGE_HINT),
+            column_name: self.error_field(PG_DIAG_COLUMN_NAME),
+            constraint_name: self.error_field(PG_DIAG_CONSTRAINT_NAME),
+            table_name: self.error_field(PG_DIAG_TABLE_NAME),
+        }
+    }
+
+    /// Get the value at the specified row and named column, or `None` if no such column exists.
+    pub fn get_value_named<T>(&self, row: i32, name: &str) -> Option<T>
+    where
+        T: std::str::FromStr,
+    {
+        self.get_value(row, self.field_number(name)?)
+    }
+
+    /// Get the raw value at the specified row and named column, or `None` if no such column exists.
+    pub fn get_value_raw_named(&self, row: i32, name: &str) -> Option<String> {
+        Some(self.get_value_raw(row, self.field_number(name)?))
+    }
+
+    /// The number of parameters of a prepared statement, for results of [`PgConn::describe_prepared`].
+    /// See also [PQnparams](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQNPARAMS).
+    pub fn nparams(&self) -> i32 {
+        unsafe { PQnparams(self.res) }
+    }
+
+    /// The type OID of the given parameter (starting at 0), for results of [`PgConn::describe_prepared`].
+    /// See also [PQparamtype](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQPARAMTYPE).
+    pub fn param_type(&self, n: i32) -> Oid {
+        unsafe { PQparamtype(self.res, n) }
+    }
+
+    /// Whether the value at the specified row and column is SQL NULL.
+    /// See also [PQgetisnull](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQGETISNULL).
+    pub fn is_null(&self, row: i32, col: i32) -> bool {
+        unsafe { PQgetisnull(self.res, row, col) == 1 }
+    }
+
+    /// A borrowed view of the given row.
+    pub fn row(&self, row: i32) -> PgRow<'_> {
+        PgRow { res: self, row }
+    }
+
+    /// Iterate over all rows of the result.
+    pub fn rows(&self) -> impl Iterator<Item = PgRow<'_>> {
+        (0..self.ntuples()).map(|row| self.row(row))
+    }
+
+    /// The values of the given row keyed by column name, `None` being SQL NULL.
+    ///
+    /// When several columns have the same name, e.g. in a join, the last one is kept.
+    pub fn row_map(&self, row: i32) -> HashMap<String, Option<String>> {
+        (0..self.nfields())
+            .map(|col| {
+                let value = (!self.is_null(row, col)).then(|| self.get_value_raw(row, col));
+                (self.field_name(col).unwrap_or_default(), value)
+            })
+            .collect()
+    }
+
+    /// Copy every value of the result, `None` being SQL NULL, into rows that can be kept
+    /// after the result is dropped.
+    pub fn into_owned_rows(&self) -> Vec<Vec<Option<String>>> {
+        (0..self.ntuples())
+            .map(|row| {
+                (0..self.nfields())
+                    .map(|col| (!self.is_null(row, col)).then(|| self.get_value_raw(row, col)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Map every row of the result to `T` via its [`FromRow`] implementation.
+    pub fn query_as<T: FromRow>(&self) -> Result<Vec<T>, PgError> {
+        self.rows().map(|row| T::from_row(&row)).collect()
+    }
+
+    /// Write the result as a JSON array with one object per row, keyed by column name.
+    ///
+    /// SQL NULL is `null`, boolean columns are JSON booleans, numeric columns are JSON numbers
+    /// (except for `NaN` and infinities) and `json`/`jsonb` values are embedded as they are;
+    /// anything else is a JSON string.
+    pub fn to_json<W: Write>(&self, w: &mut W) -> Result<(), PgError> {
+        let names: Vec<String> = (0..self.nfields())
+            .map(|col| json_string(&self.field_name(col).unwrap_or_default()))
+            .collect();
+
+        let mut out = String::from("[");
+        for row in 0..self.ntuples() {
+            if row > 0 {
+                out.push(',');
+            }
+            out.push('{');
+            for (col, name) in (0..self.nfields()).zip(&names) {
+                if col > 0 {
+                    out.push(',');
+                }
+                out.push_str(name);
+                out.push(':');
+                if self.is_null(row, col) {
+                    out.push_str("null");
+                    continue;
+                }
+                let value = self.get_value_raw(row, col);
+                match self.field_type(col) {
+                    BOOLOID => out.push_str(if value == "t" { "true" } else { "false" }),
+                    INT2OID | INT4OID | INT8OID | OIDOID | FLOAT4OID | FLOAT8OID | NUMERICOID
+                        if value.ends_with(|c: char| c.is_ascii_digit()) =>
+                    {
+                        out.push_str(&value)
+                    }
+                    JSONOID | JSONBOID => out.push_str(&value),
+                    _ => out.push_str(&json_string(&value)),
+                }
+            }
+            out.push('}');
+        }
+        out.push(']');
+
+        Ok(w.write_all(out.as_bytes())?)
+    }
+
+    /// Write the result as CSV, optionally preceded by a header line with the column names.
+    ///
+    /// Fields containing the delimiter, a double quote or a line break are quoted as in
+    /// RFC 4180, with quotes doubled. SQL NULL is an empty unquoted field, while an empty
+    /// string is written as `""`. Lines end with `\n`. The delimiter must be an ASCII
+    /// character, otherwise nothing is written and [`std::io::ErrorKind::InvalidInput`]
+    /// returned.
+    pub fn to_csv<W: Write>(&self, w: &mut W, header: bool, delimiter: u8) -> std::io::Result<()> {
+        if !delimiter.is_ascii() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "CSV delimiter is not ASCII",
+            ));
+        }
+        let delimiter = delimiter as char;
+        let mut write_line = |fields: &mut dyn Iterator<Item = Option<String>>| {
+            let mut line = String::new();
+            for (i, field) in fields.enumerate() {
+                if i > 0 {
+                    line.push(delimiter);
+                }
+                match field {
+                    None => {}
+                    Some(f) if f.is_empty() => line.push_str("\"\""),
+                    Some(f) if f.contains([delimiter, '"', '\n', '\r']) => {
+                        line.push('"');
+                        line.push_str(&f.replace('"', "\"\""));
+                        line.push('"');
+                    }
+                    Some(f) => line.push_str(&f),
+                }
+            }
+            line.push('\n');
+            w.write_all(line.as_bytes())
+        };
+
+        if header {
+            write_line(&mut (0..self.nfields()).map(|col| self.field_name(col)))?;
+        }
+        for row in 0..self.ntuples() {
+            write_line(
+                &mut (0..self.nfields())
+                    .map(|col| (!self.is_null(row, col)).then(|| self.get_value_raw(row, col))),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Create an empty result with the given status, not bound to any connection.
+    /// Columns and rows can then be filled with [`PgResult::set_attrs`] and [`PgResult::set_value`].
+    /// See also [PQmakeEmptyPGresult](https://www.postgresql.org/docs/current/libpq-misc.html#LIBPQ-PQMAKEEMPTYPGRESULT).
+    pub fn make_empty(status: ExecStatusType) -> PgResult {
+        let res = unsafe { PQmakeEmptyPGresult(null_mut(), status) };
+        assert!(!res.is_null(), "Out of memory while creating a result.");
+        PgResult { res }
+    }
+
+    /// Set the columns of a result that has none yet, as `(name, type oid)` pairs in text format.
+    /// Returns `false` if libpq refuses, e.g. because the columns were already set.
+    /// See also [PQsetResultAttrs](https://www.postgresql.org/docs/current/libpq-misc.html#LIBPQ-PQSETRESULTATTRS).
+    pub fn set_attrs(&mut self, columns: &[(&str, Oid)]) -> Result<bool, NulError> {
+        let names = columns
+            .iter()
+            .map(|(name, _)| CString::new(*name))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut descs: Vec<PGresAttDesc> = names
+            .iter()
+            .zip(columns)
+            .map(|(name, (_, typid))| PGresAttDesc {
+                name: name.as_ptr() as *mut c_char,
+                tableid: INVALID_OID,
+                columnid: 0,
+                format: 0,
+                typid: *typid,
+                typlen: -1,
+                atttypmod: -1,
+            })
+            .collect();
+        Ok(unsafe { PQsetResultAttrs(self.res, descs.len() as i32, descs.as_mut_ptr()) } == 1)
+    }
+
+    /// Set the value at the specified row and column, `None` meaning NULL.
+    /// `row` may be equal to [`PgResult::ntuples`] to append a new row.
+    /// Returns `false` if the position is out of range.
+    /// See also [PQsetvalue](https://www.postgresql.org/docs/current/libpq-misc.html#LIBPQ-PQSETVALUE).
+    pub fn set_value(&mut self, row: i32, col: i32, value: Option<&str>) -> bool {
+        let (ptr, len) = match value {
+            Some(v) => (v.as_ptr() as *mut c_char, v.len() as i32),
+            None => (null_mut(), -1),
+        };
+        unsafe { PQsetvalue(self.res, row, col, ptr, len) == 1 }
+    }
+}
+
+/// A connection option, as returned by [`PgConn::conninfo`].
+///
+/// See the [official doc](https://www.postgresql.org/docs/current/libpq-connect.html#LIBPQ-PQCONNDEFAULTS)
+/// for the meaning of each field.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConnInfoOption {
+    pub keyword: String,
+    /// The environment variable the option falls back to.
+    pub envvar: Option<String>,
+    /// The compiled-in default.
+    pub compiled: Option<String>,
+    pub value: Option<String>,
+    pub label: String,
+    /// `""` for a plain option, `"*"` for a password, `"D"` for a debug option.
+    pub dispchar: String,
+    pub dispsize: i32,
+}
+
+/// The metadata of a result column, as returned by [`PgResult::columns`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnInfo {
+    /// See [`PgResult::field_name`].
+    pub name: String,
+    /// See [`PgResult::field_type`].
+    pub type_oid: Oid,
+    /// See [`PgResult::field_modifier`].
+    pub modifier: i32,
+    /// See [`PgResult::field_size`].
+    pub size: i32,
+    /// See [`PgResult::field_format`].
+    pub format: i32,
+    /// See [`PgResult::field_table`].
+    pub table_oid: Oid,
+    /// See [`PgResult::field_table_col`].
+    pub table_col: i32,
+}
+
+/// Error fields of a result, as returned by [`PgResult::diagnostics`].
+///
+/// See the [official doc](https://www.postgresql.org/docs/current/libpq-exec.html#LIBPQ-PQRESULTERRORFIELD)
+/// for the meaning of each field; any of them may be missing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diagnostics {
+    pub severity: Option<String>,
+    pub sqlstate: Option<String>,
+    pub message_primary: Option<String>,
+    pub detail: Option<String>,
+    pub hint: Option<String>,
+    pub column_name: Option<String>,
+    pub constraint_name: Option<String>,
+    pub table_name: Option<String>,
+}
+
+/// A notice sent by the server, as returned by [`PgResult::notice`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NoticeInfo {
+    /// `DEBUG`, `LOG`, `INFO`, `NOTICE` or `WARNING`.
+    pub severity: String,
+    pub message: String,
+    pub detail: Option<String>,
+    pub hint: Option<String>,
+}
+
+/// Parameters converted for the `PQexecParams` family: text values and their types.
+struct TextParams {
+    values: Vec<Option<CString>>,
+    types: Vec<Oid>,
+}
+
+impl TextParams {
+    fn new(params: &[&dyn ToSql]) -> Result<TextParams, NulError> {
+        Ok(TextParams {
+            values: params
+                .iter()
+                .map(|p| p.to_sql().map(CString::new).transpose())
+                .collect::<Result<_, _>>()?,
+            types: params.iter().map(|p| p.type_oid()).collect(),
+        })
+    }
+
+    /// Pointers to the values, null for SQL NULL, valid as long as `self`.
+    fn values(&self) -> Vec<*const c_char> {
+        self.values
+            .iter()
+            .map(|v| v.as_ref().map_or(std::ptr::null(), |v| v.as_ptr()))
+            .collect()
+    }
+}
+
+/// Types that can be bound as a query parameter, see [`PgConn::exec_params`].
+pub trait ToSql {
+    /// The value in Postgres text format, `None` being SQL NULL.
+    fn to_sql(&self) -> Option<String>;
+
+    /// The type of the parameter, or [`INVALID_OID`] to let the server infer it.
+    fn type_oid(&self) -> Oid;
+}
+
+/// Types that can be decoded from a value in Postgres text format, see [`PgResult::get`].
+pub trait FromSql: Sized {
+    /// Decode a non-NULL value of a column of type `oid`, `None` if it is not valid.
+    fn from_sql(value: &str, oid: Oid) -> Option<Self>;
+
+    /// The value to decode SQL NULL into, by default none.
+    fn from_sql_null() -> Option<Self> {
+        None
+    }
+}
+
+macro_rules! impl_sql_via_str {
+    ($($t:ty => $oid:expr),* $(,)?) => {
+        $(
+            impl ToSql for $t {
+                fn to_sql(&self) -> Option<String> {
+                    Some(self.to_string())
+                }
+
+                fn type_oid(&self) -> Oid {
+                    $oid
+                }
+            }
+
+            impl FromSql for $t {
+                fn from_sql(value: &str, _oid: Oid) -> Option<Self> {
+                    value.parse().ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_sql_via_str! {
+    i16 => INT2OID,
+    i32 => INT4OID,
+    i64 => INT8OID,
+    f32 => FLOAT4OID,
+    f64 => FLOAT8OID,
+    String => INVALID_OID,
+}
+
+impl ToSql for &str {
+    fn to_sql(&self) -> Option<String> {
+        Some(self.to_string())
+    }
+
+    fn type_oid(&self) -> Oid {
+        INVALID_OID
+    }
+}
+
+impl ToSql for bool {
+    fn to_sql(&self) -> Option<String> {
+        Some(if *self { "t" } else { "f" }.to_string())
+    }
+
+    fn type_oid(&self) -> Oid {
+        BOOLOID
+    }
+}
+
+impl FromSql for bool {
+    fn from_sql(value: &str, _oid: Oid) -> Option<Self> {
+        match value {
+            "t" | "true" | "1" => Some(true),
+            "f" | "false" | "0" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+impl<T: ToSql> ToSql for Option<T> {
+    fn to_sql(&self) -> Option<String> {
+        self.as_ref().and_then(ToSql::to_sql)
+    }
+
+    fn type_oid(&self) -> Oid {
+        self.as_ref().map_or(INVALID_OID, ToSql::type_oid)
+    }
+}
+
+impl<T: FromSql> FromSql for Option<T> {
+    fn from_sql(value: &str, oid: Oid) -> Option<Self> {
+        T::from_sql(value, oid).map(Some)
+    }
+
+    fn from_sql_null() -> Option<Self> {
+        Some(None)
+    }
+}
+
+/// Parses `json` and `jsonb` values; SQL NULL is decoded as [`serde_json::Value::Null`].
+#[cfg(feature = "json")]
+impl FromSql for serde_json::Value {
+    fn from_sql(value: &str, _oid: Oid) -> Option<Self> {
+        serde_json::from_str(value).ok()
+    }
+
+    fn from_sql_null() -> Option<Self> {
+        Some(serde_json::Value::Null)
+    }
+}
+
+/// Parses `date` values in the ISO format, e.g. `2024-01-02`; `infinity` and BC dates are
+/// not valid.
+#[cfg(feature = "chrono")]
+impl FromSql for chrono::NaiveDate {
+    fn from_sql(value: &str, _oid: Oid) -> Option<Self> {
+        chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+    }
+}
+
+/// Parses `timestamp` values in the ISO format, e.g. `2024-01-02 03:04:05.678`.
+#[cfg(feature = "chrono")]
+impl FromSql for chrono::NaiveDateTime {
+    fn from_sql(value: &str, _oid: Oid) -> Option<Self> {
+        chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f").ok()
+    }
+}
+
+/// Parses `timestamptz` values in the ISO format, whose offset may omit the minutes, e.g.
+/// `2024-01-02 03:04:05+00` or `2024-01-02 08:34:05+05:30`.
+#[cfg(feature = "chrono")]
+impl FromSql for chrono::DateTime<chrono::Utc> {
+    fn from_sql(value: &str, _oid: Oid) -> Option<Self> {
+        chrono::DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f%#z")
+            .ok()
+            .map(|t| t.with_timezone(&chrono::Utc))
+    }
+}
+
+/// Parses and emits `uuid` values in their canonical hyphenated form.
+#[cfg(feature = "uuid")]
+impl FromSql for uuid::Uuid {
+    fn from_sql(value: &str, _oid: Oid) -> Option<Self> {
+        uuid::Uuid::try_parse(value).ok()
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl ToSql for uuid::Uuid {
+    fn to_sql(&self) -> Option<String> {
+        Some(self.hyphenated().to_string())
+    }
+
+    fn type_oid(&self) -> Oid {
+        UUIDOID
+    }
+}
+
+/// Parses and emits `numeric` values exactly; values with more than 28 decimal digits, and
+/// `NaN` or infinities, are not valid.
+#[cfg(feature = "rust_decimal")]
+impl FromSql for rust_decimal::Decimal {
+    fn from_sql(value: &str, _oid: Oid) -> Option<Self> {
+        rust_decimal::Decimal::from_str_exact(value).ok()
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+impl ToSql for rust_decimal::Decimal {
+    fn to_sql(&self) -> Option<String> {
+        Some(self.to_string())
+    }
+
+    fn type_oid(&self) -> Oid {
+        NUMERICOID
+    }
+}
+
+/// A column reference within a [`PgRow`], either by position or by name.
+pub trait ColumnIndex {
+    fn column_index(&self, res: &PgResult) -> Result<i32, PgError>;
+}
+
+impl ColumnIndex for i32 {
+    fn column_index(&self, res: &PgResult) -> Result<i32, PgError> {
+        if (0..res.nfields()).contains(self) {
+            Ok(*self)
+        } else {
+            Err(PgError::ColumnNotFound(self.to_string()))
+        }
+    }
+}
+
+impl ColumnIndex for &str {
+    fn column_index(&self, res: &PgResult) -> Result<i32, PgError> {
+        res.field_number(self)
+            .ok_or_else(|| PgError::ColumnNotFound(self.to_string()))
+    }
+}
+
+/// A single row of a [`PgResult`].
+pub struct PgRow<'a> {
+    res: &'a PgResult,
+    row: i32,
+}
+
+impl PgRow<'_> {
+    /// The index of this row within its result.
+    pub fn index(&self) -> i32 {
+        self.row
+    }
+
+    /// Decode the value of the given column, referenced by index or by name, as
+    /// [`PgResult::get`] does.
+    pub fn get<T, C>(&self, col: C) -> Result<T, PgError>
+    where
+        T: FromSql,
+        C: ColumnIndex,
+    {
+        self.res.get(self.row, col.column_index(self.res)?)
+    }
+}
+
+/// Types that can be built from a single row of a result.
+///
+/// See [`PgResult::query_as`].
+pub trait FromRow: Sized {
+    fn from_row(row: &PgRow) -> Result<Self, PgError>;
+}
+
+/// Split a one-dimensional array literal such as `{1,"a \"b\"",NULL}` into its elements,
+/// `None` standing for NULL. An optional `[lower:upper]=` dimension prefix is skipped;
+/// nested arrays and malformed literals give `None`.
+fn parse_array_literal(s: &str) -> Option<Vec<Option<String>>> {
+    let s = if s.starts_with('[') {
+        &s[s.find('=')? + 1..]
+    } else {
+        s
+    };
+    let inner = s.strip_prefix('{')?.strip_suffix('}')?;
+    let mut items = Vec::new();
+    if inner.is_empty() {
+        return Some(items);
+    }
+
+    let mut chars = inner.chars().peekable();
+    loop {
+        let mut item = String::new();
+        let quoted = chars.peek() == Some(&'"');
+        if quoted {
+            chars.next();
+            loop {
+                match chars.next()? {
+                    '\\' => item.push(chars.next()?),
+                    '"' => break,
+                    c => item.push(c),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                match c {
+                    ',' => break,
+                    '{' | '}' | '"' => return None,
+                    '\\' => {
+                        chars.next();
+                        item.push(chars.next()?);
+                    }
+                    c => {
+                        chars.next();
+                        item.push(c);
+                    }
+                }
+            }
+            if item.is_empty() {
+                return None;
+            }
+        }
+
+        items.push(if !quoted && item.eq_ignore_ascii_case("NULL") {
+            None
+        } else {
+            Some(item)
+        });
+
+        match chars.next() {
+            None => return Some(items),
+            Some(',') => continue,
+            Some(_) => return None,
+        }
+    }
+}
+
+/// The argument libpq passes to an installed notice closure: the closure itself, or null for
+/// libpq's default processor and receiver, which ignore it.
+fn closure_ptr(closure: &mut Option<Box<dyn Send>>) -> *mut c_void {
+    closure
+        .as_mut()
+        .map_or(null_mut(), |b| b.as_mut() as *mut dyn Send as *mut c_void)
+}
+
+/// The bytes of `s` followed by a NUL, for the libpq functions that read a single character
+/// and stop at a terminator.
+fn nul_terminated(s: &str) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(s.len() + 1);
+    buf.extend_from_slice(s.as_bytes());
+    buf.push(0);
+    buf
+}
+
+/// A duration as a number of seconds, rounded up so that a sub-second one doesn't become
+/// zero, which libpq reads as "use the default".
+fn whole_seconds(duration: std::time::Duration) -> String {
+    duration.as_nanos().div_ceil(1_000_000_000).to_string()
+}
+
+/// The log level matching the severity prefix of a notice, and the message following it.
+#[cfg(feature = "log")]
+fn notice_level(notice: &str) -> (log::Level, &str) {
+    let levels = [
+        ("WARNING:", log::Level::Warn),
+        ("NOTICE:", log::Level::Info),
+        ("INFO:", log::Level::Info),
+        ("LOG:", log::Level::Info),
+        ("DEBUG:", log::Level::Debug),
+    ];
+    for (prefix, level) in levels {
+        if let Some(message) = notice.strip_prefix(prefix) {
+            return (level, message.trim_start());
+        }
+    }
+    (log::Level::Info, notice)
 }
 
+/// Quote and escape a string as a JSON string literal.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Copy a string owned by libpq, `None` if the pointer is null.
+unsafe fn opt_string(s: *const c_char) -> Option<String> {
+    if s.is_null() {
+        None
+    } else {
+        Some(
+            unsafe { std::ffi::CStr::from_ptr(s) }
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+}
+
+/// A buffer allocated by libpq, freed with `PQfreemem` when dropped.
+pub(crate) struct PqMem(*mut c_void);
+
+impl PqMem {
+    /// Take ownership of `ptr`, or `None` if it is null.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be null or a buffer that libpq allocated and documents as freed with
+    /// `PQfreemem`, not owned by anything else.
+    pub(crate) unsafe fn new<T>(ptr: *mut T) -> Option<PqMem> {
+        (!ptr.is_null()).then(|| PqMem(ptr as *mut c_void))
+    }
+
+    /// The buffer as a NUL-terminated string, with invalid UTF-8 replaced.
+    ///
+    /// # Safety
+    ///
+    /// The buffer must be NUL-terminated.
+    pub(crate) unsafe fn to_string_lossy(&self) -> String {
+        unsafe { std::ffi::CStr::from_ptr(self.0 as *const c_char) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// The first `len` bytes of the buffer.
+    ///
+    /// # Safety
+    ///
+    /// The buffer must be at least `len` bytes long.
+    pub(crate) unsafe fn as_bytes(&self, len: usize) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.0 as *const u8, len) }
+    }
+}
+
+impl Drop for PqMem {
+    fn drop(&mut self) {
+        unsafe { PQfreemem(self.0) }
+    }
+}
+
+/// Copy a string owned by libpq, requiring valid UTF-8; a null pointer is an empty string.
+unsafe fn strict_string(s: *const c_char) -> Result<String, Utf8Error> {
+    if s.is_null() {
+        Ok(String::new())
+    } else {
+        unsafe { std::ffi::CStr::from_ptr(s) }
+            .to_str()
+            .map(str::to_string)
+    }
+}
+
+/// Whether `PQprint` would right-align a value as a number: only digits, `.`, `e`/`E`,
+/// spaces and `-`, not starting with an exponent marker and ending with a digit.
+fn looks_numeric(s: &str) -> bool {
+    s.chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | ' ' | '-'))
+        && !s.starts_with(['e', 'E'])
+        && s.ends_with(|c: char| c.is_ascii_digit())
+}
+
+/// Renders the result as an aligned table, laid out like [`PgResult::print`] does with
+/// `header`, `align` and `standard` set and `"|"` as field separator, but without any IO.
 impl Display for PgResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut temp_file = Builder::new()
-            .prefix("pg-res-")
-            .suffix(".json")
-            .tempfile()
-            .unwrap();
-
-        let temp_path = temp_file.path().to_path_buf();
-
-        self.print(
-            temp_path.as_path().to_str().unwrap(),
-            true,
-            true,
-            "|",
-            true,
-            false,
-            false,
-            false,
-        );
-
-        let mut s = String::new();
-        temp_file
-            .seek(std::io::SeekFrom::Start(0))
-            .expect("Failed to seek to start of temp file.");
-        temp_file
-            .read_to_string(&mut s)
-            .expect("Failed to read temp file.");
-
-        write!(f, "{}", s)
+        let nfields = self.nfields();
+        if nfields <= 0 {
+            return Ok(());
+        }
+        let ntuples = self.ntuples();
+
+        let names: Vec<String> = (0..nfields)
+            .map(|col| self.field_name(col).unwrap_or_default())
+            .collect();
+        let rows: Vec<Vec<String>> = (0..ntuples)
+            .map(|row| {
+                (0..nfields)
+                    .map(|col| self.get_value_raw(row, col))
+                    .collect()
+            })
+            .collect();
+
+        // Widths are in bytes, as in PQprint; empty values don't affect alignment.
+        let mut widths: Vec<usize> = names.iter().map(|n| n.len()).collect();
+        let mut numeric = vec![true; names.len()];
+        for row in &rows {
+            for (col, value) in row.iter().enumerate() {
+                if !value.is_empty() {
+                    widths[col] = widths[col].max(value.len());
+                    numeric[col] = numeric[col] && looks_numeric(value);
+                }
+            }
+        }
+
+        let mut border = String::from("+");
+        for width in &widths {
+            border.push_str(&"-".repeat(width + 2));
+            border.push('+');
+        }
+
+        let write_line = |f: &mut std::fmt::Formatter<'_>, values: &[String]| {
+            write!(f, "|")?;
+            for (col, value) in values.iter().enumerate() {
+                let pad = " ".repeat(widths[col] - value.len());
+                if numeric[col] {
+                    write!(f, " {}{} |", pad, value)?;
+                } else {
+                    write!(f, " {}{} |", value, pad)?;
+                }
+            }
+            writeln!(f)
+        };
+
+        writeln!(f, "{}", border)?;
+        write_line(f, &names)?;
+        writeln!(f, "{}", border)?;
+        for row in &rows {
+            write_line(f, row)?;
+            writeln!(f, "{}", border)?;
+        }
+
+        writeln!(
+            f,
+            "({} row{})\n",
+            ntuples,
+            if ntuples == 1 { "" } else { "s" }
+        )
     }
 }
@@ -0,0 +1,92 @@
+use crate::{
+    PGpipelineStatus, PQenterPipelineMode, PQexitPipelineMode, PQpipelineStatus, PQpipelineSync,
+    PQsendFlushRequest, PgConn, PgError, PgResult,
+};
+
+impl PgConn {
+    /// Switch the connection to pipeline mode, where queries submitted with the `send_*`
+    /// methods (other than [`PgConn::send_query`]) are sent without waiting for the results of
+    /// the previous ones. Fails if a query is in progress.
+    /// See also [PQenterPipelineMode](https://www.postgresql.org/docs/current/libpq-pipeline-mode.html#LIBPQ-PQENTERPIPELINEMODE).
+    pub fn enter_pipeline_mode(&mut self) -> Result<(), PgError> {
+        match unsafe { PQenterPipelineMode(self.conn) } {
+            1 => Ok(()),
+            _ => Err(PgError::Connection(self.error_message())),
+        }
+    }
+
+    /// Leave pipeline mode, which fails while results are pending.
+    /// See also [PQexitPipelineMode](https://www.postgresql.org/docs/current/libpq-pipeline-mode.html#LIBPQ-PQEXITPIPELINEMODE).
+    pub fn exit_pipeline_mode(&mut self) -> Result<(), PgError> {
+        match unsafe { PQexitPipelineMode(self.conn) } {
+            1 => Ok(()),
+            _ => Err(PgError::Connection(self.error_message())),
+        }
+    }
+
+    /// Whether the connection is in pipeline mode, and if so whether a query of the current
+    /// pipeline segment failed: `PQ_PIPELINE_OFF`, `PQ_PIPELINE_ON` or `PQ_PIPELINE_ABORTED`.
+    /// See also [PQpipelineStatus](https://www.postgresql.org/docs/current/libpq-pipeline-mode.html#LIBPQ-PQPIPELINESTATUS).
+    pub fn pipeline_status(&self) -> PGpipelineStatus {
+        unsafe { PQpipelineStatus(self.conn) }
+    }
+
+    /// Mark a sync point in the pipeline and send the queued queries to the server.
+    ///
+    /// Once a query fails, the server skips the following ones, whose results have status
+    /// `PGRES_PIPELINE_ABORTED`, up to the sync point, reported by a `PGRES_PIPELINE_SYNC`
+    /// result; the queries submitted after it run normally.
+    /// See also [PQpipelineSync](https://www.postgresql.org/docs/current/libpq-pipeline-mode.html#LIBPQ-PQPIPELINESYNC).
+    pub fn pipeline_sync(&mut self) -> Result<(), PgError> {
+        match unsafe { PQpipelineSync(self.conn) } {
+            1 => Ok(()),
+            _ => Err(PgError::Connection(self.error_message())),
+        }
+    }
+
+    /// Ask the server to send the results of the queries submitted so far, without
+    /// establishing a sync point; call [`PgConn::flush`] to actually send the request.
+    /// See also [PQsendFlushRequest](https://www.postgresql.org/docs/current/libpq-pipeline-mode.html#LIBPQ-PQSENDFLUSHREQUEST).
+    pub fn send_flush_request(&mut self) -> Result<(), PgError> {
+        match unsafe { PQsendFlushRequest(self.conn) } {
+            1 => Ok(()),
+            _ => Err(PgError::Connection(self.error_message())),
+        }
+    }
+
+    /// Collect the results of the pipeline up to the next sync point, submitted with
+    /// [`PgConn::pipeline_sync`]: one result per query, in the order they were submitted,
+    /// followed by the `PGRES_PIPELINE_SYNC` result.
+    ///
+    /// The results are returned unchecked, so that the failing query can be told apart from
+    /// the ones skipped after it with [`PgResult::is_error`] and
+    /// [`PgResult::is_pipeline_aborted`]. A query producing several results, e.g. in
+    /// [chunked rows mode](PgConn::set_chunked_rows_mode), is represented by its last one.
+    /// Fails if no sync point is pending.
+    pub fn pipeline_results(&mut self) -> Result<Vec<PgResult>, PgError> {
+        let mut results = Vec::new();
+        let mut last = None;
+        let mut ended = true;
+        loop {
+            match self.get_result() {
+                Some(res) if res.is_pipeline_sync() => {
+                    results.push(res);
+                    return Ok(results);
+                }
+                Some(res) => {
+                    last = Some(res);
+                    ended = false;
+                }
+                // Each query's results are followed by a `None`; two in a row mean that
+                // nothing is left to read.
+                None if ended => {
+                    return Err(PgError::Connection("no pipeline sync pending".to_string()));
+                }
+                None => {
+                    results.extend(last.take());
+                    ended = true;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{Condvar, Mutex},
+};
+
+use crate::{
+    PGTransactionStatusType_PQTRANS_IDLE, PGTransactionStatusType_PQTRANS_INERROR,
+    PGTransactionStatusType_PQTRANS_INTRANS, PGpipelineStatus_PQ_PIPELINE_OFF, PQtransactionStatus,
+    PgConn, PgConnBuilder, PgError, PgResult,
+};
+
+/// A bounded pool of connections, all opened with the same [`PgConnBuilder`].
+///
+/// Connections are opened lazily, up to `max_size`; once all of them are in use,
+/// [`PgPool::get`] blocks until one is returned.
+pub struct PgPool {
+    builder: PgConnBuilder,
+    max_size: usize,
+    state: Mutex<PoolState>,
+    returned: Condvar,
+}
+
+struct PoolState {
+    idle: Vec<PgConn>,
+    // Connections alive, either idle or checked out.
+    open: usize,
+}
+
+/// A connection checked out from a [`PgPool`], returned to it on drop.
+///
+/// A transaction left open is rolled back first; a connection still running a query or a
+/// COPY, with unread results or in pipeline mode is closed instead.
+pub struct PooledConn<'a> {
+    pool: &'a PgPool,
+    conn: Option<PgConn>,
+}
+
+impl PgPool {
+    pub fn new(builder: PgConnBuilder, max_size: usize) -> PgPool {
+        assert!(
+            max_size > 0,
+            "A pool needs room for at least one connection."
+        );
+        PgPool {
+            builder,
+            max_size,
+            state: Mutex::new(PoolState {
+                idle: Vec::new(),
+                open: 0,
+            }),
+            returned: Condvar::new(),
+        }
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// The number of connections currently open, checked out or not.
+    pub fn size(&self) -> usize {
+        self.state.lock().unwrap().open
+    }
+
+    /// Check out a connection, reusing an idle one or opening a new one while below
+    /// `max_size`, otherwise waiting for one to be returned.
+    ///
+    /// An idle connection whose status is no longer `CONNECTION_OK` is first
+    /// [reconnected](PgConn::reconnect), and discarded if that fails.
+    pub fn get(&self) -> Result<PooledConn<'_>, PgError> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(mut conn) = state.idle.pop() {
+                drop(state);
+                if conn.is_ok() || conn.reconnect().is_ok() {
+                    return Ok(PooledConn {
+                        pool: self,
+                        conn: Some(conn),
+                    });
+                }
+                drop(conn);
+                state = self.state.lock().unwrap();
+                state.open -= 1;
+            } else if state.open < self.max_size {
+                state.open += 1;
+                drop(state);
+                return match self.builder.connect() {
+                    Ok(conn) => Ok(PooledConn {
+                        pool: self,
+                        conn: Some(conn),
+                    }),
+                    Err(e) => {
+                        self.state.lock().unwrap().open -= 1;
+                        self.returned.notify_one();
+                        Err(e)
+                    }
+                };
+            } else {
+                state = self.returned.wait(state).unwrap();
+            }
+        }
+    }
+}
+
+impl Deref for PooledConn<'_> {
+    type Target = PgConn;
+
+    fn deref(&self) -> &PgConn {
+        self.conn.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledConn<'_> {
+    fn deref_mut(&mut self) -> &mut PgConn {
+        self.conn.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledConn<'_> {
+    fn drop(&mut self) {
+        if let Some(mut conn) = self.conn.take() {
+            if reusable(&mut conn) {
+                self.pool.state.lock().unwrap().idle.push(conn);
+            } else {
+                drop(conn);
+                self.pool.state.lock().unwrap().open -= 1;
+            }
+            self.pool.returned.notify_one();
+        }
+    }
+}
+
+/// Whether a returned connection can be checked out again, after rolling back the
+/// transaction its user left open, if any.
+///
+/// A broken connection is kept, to be reconnected by [`PgPool::get`].
+fn reusable(conn: &mut PgConn) -> bool {
+    if !conn.is_ok() {
+        return true;
+    }
+    if conn.pipeline_status() != PGpipelineStatus_PQ_PIPELINE_OFF {
+        return false;
+    }
+    #[allow(non_upper_case_globals)]
+    match unsafe { PQtransactionStatus(conn.conn) } {
+        PGTransactionStatusType_PQTRANS_IDLE => true,
+        PGTransactionStatusType_PQTRANS_INTRANS | PGTransactionStatusType_PQTRANS_INERROR => conn
+            .exec("ROLLBACK;")
+            .map_err(PgError::from)
+            .and_then(PgResult::check)
+            .is_ok(),
+        // A query or a COPY is in progress, or its results were not all read.
+        _ => false,
+    }
+}
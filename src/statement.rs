@@ -0,0 +1,156 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{Oid, PgConn, PgError, PgResult, ToSql};
+
+// The number of statements `exec_cached` keeps prepared unless configured otherwise.
+const DEFAULT_CACHE_CAPACITY: usize = 64;
+
+// Numbers the statements prepared by this crate, so that their names are unique.
+static NEXT_STATEMENT: AtomicU64 = AtomicU64::new(0);
+
+/// A statement prepared by [`PgConn::prepare_statement`] under a generated name.
+///
+/// The handle holds only the name, leaving the connection free for any other use; the
+/// connection the statement was prepared on is passed to [`PreparedStatement::execute`].
+/// Deallocate it with [`PreparedStatement::close`]: a handle dropped instead leaves the
+/// statement prepared until the session ends, since it cannot reach the connection.
+#[derive(Debug)]
+pub struct PreparedStatement {
+    name: String,
+}
+
+/// The statements prepared by [`PgConn::exec_cached`], by SQL text.
+pub(crate) struct StatementCache {
+    names: HashMap<String, String>,
+    // The SQL texts, oldest first, for eviction.
+    order: VecDeque<String>,
+    capacity: usize,
+}
+
+impl StatementCache {
+    pub(crate) fn new() -> StatementCache {
+        StatementCache {
+            names: HashMap::new(),
+            order: VecDeque::new(),
+            capacity: DEFAULT_CACHE_CAPACITY,
+        }
+    }
+
+    /// Forget every statement without deallocating it, e.g. once the session they were
+    /// prepared in is gone.
+    pub(crate) fn forget(&mut self) {
+        self.names.clear();
+        self.order.clear();
+    }
+}
+
+impl PgConn {
+    /// Prepare `query` under a generated name, see [`PgConn::prepare`] for `param_types`.
+    pub fn prepare_statement(
+        &mut self,
+        query: &str,
+        param_types: &[Oid],
+    ) -> Result<PreparedStatement, PgError> {
+        let name = statement_name();
+        self.prepare(&name, query, param_types)?.check()?;
+        Ok(PreparedStatement { name })
+    }
+}
+
+impl PgConn {
+    /// Execute `sql` with the given parameters as a prepared statement, prepared on first
+    /// use and reused by later calls with the same SQL text.
+    ///
+    /// The parameter types of the statement are those of the parameters of the first call.
+    /// Once the cache is full, the oldest statement is deallocated to make room; with a
+    /// [capacity](PgConn::set_statement_cache_capacity) of 0, `sql` is run with
+    /// [`PgConn::exec_params`] instead.
+    pub fn exec_cached(&mut self, sql: &str, params: &[&dyn ToSql]) -> Result<PgResult, PgError> {
+        if self.statement_cache.capacity == 0 {
+            return self.exec_params(sql, params);
+        }
+        if let Some(name) = self.statement_cache.names.get(sql) {
+            return self.exec_prepared(name, params);
+        }
+
+        while self.statement_cache.order.len() >= self.statement_cache.capacity {
+            self.evict_statement();
+        }
+        let name = statement_name();
+        let types: Vec<Oid> = params.iter().map(|p| p.type_oid()).collect();
+        self.prepare(&name, sql, &types)?.check()?;
+        let res = self.exec_prepared(&name, params);
+        self.statement_cache.names.insert(sql.to_string(), name);
+        self.statement_cache.order.push_back(sql.to_string());
+        res
+    }
+
+    /// The number of statements [`PgConn::exec_cached`] keeps prepared, 64 by default.
+    pub fn statement_cache_capacity(&self) -> usize {
+        self.statement_cache.capacity
+    }
+
+    /// Change the number of statements [`PgConn::exec_cached`] keeps prepared, deallocating
+    /// the oldest ones beyond it.
+    pub fn set_statement_cache_capacity(&mut self, capacity: usize) {
+        self.statement_cache.capacity = capacity;
+        while self.statement_cache.order.len() > capacity {
+            self.evict_statement();
+        }
+    }
+
+    /// Deallocate every statement prepared by [`PgConn::exec_cached`].
+    pub fn clear_statement_cache(&mut self) {
+        while !self.statement_cache.order.is_empty() {
+            self.evict_statement();
+        }
+    }
+
+    /// Deallocate the oldest statement of the cache.
+    fn evict_statement(&mut self) {
+        if let Some(sql) = self.statement_cache.order.pop_front()
+            && let Some(name) = self.statement_cache.names.remove(&sql)
+        {
+            deallocate(self, &name);
+        }
+    }
+}
+
+impl PreparedStatement {
+    /// The generated name of the statement, e.g. to look it up in `pg_prepared_statements`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Execute the statement on `conn`, where it was prepared, with the given parameters,
+    /// see [`PgConn::exec_prepared`].
+    pub fn execute(&self, conn: &PgConn, params: &[&dyn ToSql]) -> Result<PgResult, PgError> {
+        conn.exec_prepared(&self.name, params)
+    }
+
+    /// Deallocate the statement on `conn`, where it was prepared.
+    pub fn close(self, conn: &PgConn) -> Result<(), PgError> {
+        let name = conn.escape_identifier(&self.name)?;
+        conn.exec(&format!("DEALLOCATE {};", name))?.check()?;
+        Ok(())
+    }
+}
+
+/// Deallocate the prepared statement `name`, ignoring failures: within an aborted
+/// transaction, the statement is left until the session ends.
+fn deallocate(conn: &PgConn, name: &str) {
+    if let Ok(name) = conn.escape_identifier(name) {
+        let _ = conn.exec(&format!("DEALLOCATE {};", name));
+    }
+}
+
+/// A name for a new prepared statement, unique within the process.
+fn statement_name() -> String {
+    format!(
+        "libpq_rs_stmt_{}",
+        NEXT_STATEMENT.fetch_add(1, Ordering::Relaxed)
+    )
+}
@@ -0,0 +1,157 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::{PgConn, PgError};
+
+/// A transaction opened by [`PgConn::transaction`], rolled back on drop unless committed.
+///
+/// The connection is reachable through the guard while the transaction is open.
+pub struct Transaction<'a> {
+    conn: &'a mut PgConn,
+    done: bool,
+}
+
+/// A savepoint within a [`Transaction`] (or within another savepoint), rolled back to on
+/// drop unless released with [`Savepoint::commit`].
+pub struct Savepoint<'a> {
+    conn: &'a mut PgConn,
+    // The name, already escaped as an identifier.
+    name: String,
+    done: bool,
+}
+
+impl PgConn {
+    /// Start a transaction with `BEGIN`.
+    pub fn transaction(&mut self) -> Result<Transaction<'_>, PgError> {
+        self.exec("BEGIN;")?.check()?;
+        Ok(Transaction {
+            conn: self,
+            done: false,
+        })
+    }
+
+    /// Run `f` within a transaction, committed if it returns `Ok` and rolled back if it
+    /// returns `Err` or panics.
+    ///
+    /// Failures to begin or commit are converted into `E`; the error returned by `f` takes
+    /// precedence over a failure to roll back, which is ignored.
+    pub fn with_transaction<T, E, F>(&mut self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Transaction<'_>) -> Result<T, E>,
+        E: From<PgError>,
+    {
+        let mut tx = self.transaction()?;
+        match f(&mut tx) {
+            Ok(value) => {
+                tx.commit()?;
+                Ok(value)
+            }
+            Err(e) => {
+                let _ = tx.rollback();
+                Err(e)
+            }
+        }
+    }
+
+    fn savepoint_guard(&mut self, name: &str) -> Result<Savepoint<'_>, PgError> {
+        let name = self.escape_identifier(name)?;
+        self.exec(&format!("SAVEPOINT {};", name))?.check()?;
+        Ok(Savepoint {
+            conn: self,
+            name,
+            done: false,
+        })
+    }
+}
+
+impl Transaction<'_> {
+    /// Commit the transaction with `COMMIT`.
+    pub fn commit(mut self) -> Result<(), PgError> {
+        self.done = true;
+        self.conn.exec("COMMIT;")?.check()?;
+        Ok(())
+    }
+
+    /// Roll the transaction back with `ROLLBACK`, as dropping it does, but reporting errors.
+    pub fn rollback(mut self) -> Result<(), PgError> {
+        self.done = true;
+        self.conn.exec("ROLLBACK;")?.check()?;
+        Ok(())
+    }
+
+    /// Define a savepoint with `SAVEPOINT`, its name escaped as an identifier.
+    pub fn savepoint(&mut self, name: &str) -> Result<Savepoint<'_>, PgError> {
+        self.conn.savepoint_guard(name)
+    }
+}
+
+impl Savepoint<'_> {
+    /// Keep the changes made since the savepoint with `RELEASE SAVEPOINT`.
+    pub fn commit(mut self) -> Result<(), PgError> {
+        self.done = true;
+        self.conn
+            .exec(&format!("RELEASE SAVEPOINT {};", self.name))?
+            .check()?;
+        Ok(())
+    }
+
+    /// Undo the changes made since the savepoint with `ROLLBACK TO SAVEPOINT`, as dropping
+    /// it does, but reporting errors.
+    pub fn rollback(mut self) -> Result<(), PgError> {
+        self.done = true;
+        self.conn
+            .exec(&format!("ROLLBACK TO SAVEPOINT {};", self.name))?
+            .check()?;
+        Ok(())
+    }
+
+    /// Define a savepoint nested within this one.
+    pub fn savepoint(&mut self, name: &str) -> Result<Savepoint<'_>, PgError> {
+        self.conn.savepoint_guard(name)
+    }
+}
+
+impl Deref for Transaction<'_> {
+    type Target = PgConn;
+
+    fn deref(&self) -> &PgConn {
+        self.conn
+    }
+}
+
+impl DerefMut for Transaction<'_> {
+    fn deref_mut(&mut self) -> &mut PgConn {
+        self.conn
+    }
+}
+
+impl Deref for Savepoint<'_> {
+    type Target = PgConn;
+
+    fn deref(&self) -> &PgConn {
+        self.conn
+    }
+}
+
+impl DerefMut for Savepoint<'_> {
+    fn deref_mut(&mut self) -> &mut PgConn {
+        self.conn
+    }
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = self.conn.exec("ROLLBACK;");
+        }
+    }
+}
+
+impl Drop for Savepoint<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = self
+                .conn
+                .exec(&format!("ROLLBACK TO SAVEPOINT {};", self.name));
+        }
+    }
+}
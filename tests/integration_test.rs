@@ -1,9 +1,26 @@
-use std::{fs, ops::ControlFlow, thread};
+use std::{
+    fs,
+    io::{Cursor, Read, Seek, SeekFrom, Write},
+    ops::ControlFlow,
+    os::fd::{AsFd, AsRawFd},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 use libpq::{
-    ConnStatusType_CONNECTION_OK, ExecStatusType_PGRES_COMMAND_OK,
-    ExecStatusType_PGRES_FATAL_ERROR, ExecStatusType_PGRES_TUPLES_OK, PG_DIAG_SEVERITY,
-    PQlibVersion, PgConn,
+    BOOLOID, ColumnInfo, ConnInfoOption, ConnStatus, ConnStatusType, ConnStatusType_CONNECTION_BAD,
+    ConnStatusType_CONNECTION_OK, ContextVisibility, Diagnostics, ExecStatus, ExecStatusType,
+    ExecStatusType_PGRES_COMMAND_OK, ExecStatusType_PGRES_COPY_IN, ExecStatusType_PGRES_COPY_OUT,
+    ExecStatusType_PGRES_EMPTY_QUERY, ExecStatusType_PGRES_FATAL_ERROR,
+    ExecStatusType_PGRES_TUPLES_OK, FromRow, INT4OID, INT8OID, INVALID_OID, JSONOID, NoticeInfo,
+    PG_DIAG_SEVERITY, PGpipelineStatus_PQ_PIPELINE_OFF, PGpipelineStatus_PQ_PIPELINE_ON,
+    PQlibVersion, PgConn, PgConnBuilder, PgError, PgResult, PgRow, PgSocketPollResult, TEXTOID,
+    ToBinary, VARCHAROID, Verbosity, copy_escape_field, is_threadsafe, type_name,
 };
 
 #[test]
@@ -126,13 +143,14 @@ fn listen_notify() {
 /// - **Listener thread**
 ///   - Connects via `PgConn::connect_db_env_vars()`.
 ///   - Asserts the connection is OK: `ConnStatusType_CONNECTION_OK`.
-///   - Executes `LISTEN TBL3` and asserts `ExecStatusType_PGRES_COMMAND_OK`.
+///   - Calls `conn.listen("TBL3")` and asserts `ExecStatusType_PGRES_COMMAND_OK`.
 ///   - Calls `conn.listen(Some(1.0), callback)` to collect notifications.
 ///
 /// - **Main thread (sender)**
 ///   - Sleeps `100ms` to give the listener time to subscribe.
 ///   - Connects via `PgConn::connect_db_env_vars()` and checks status.
-///   - Executes `NOTIFY TBL3` **five times**, asserting `PGRES_COMMAND_OK` each time.
+///   - Calls `conn.notify("TBL3", None)` **five times**, asserting `PGRES_TUPLES_OK` each time
+///     (it runs `SELECT pg_notify(...)`).
 ///
 /// ### How notifications are received
 ///
@@ -152,11 +170,12 @@ fn listen_notify() {
 /// After joining the listener thread:
 ///
 /// - `recvs.len() == 5`
-/// - `recvs == vec!["tbl3", "tbl3", "tbl3", "tbl3", "tbl3"]`
+/// - `recvs == vec!["TBL3", "TBL3", "TBL3", "TBL3", "TBL3"]`
 ///
 /// ### Notes
 ///
-/// PostgreSQL folds unquoted identifiers to lowercase, so `TBL3` is received as `"tbl3"`.
+/// `listen` quotes the channel name and `notify` binds it as a parameter, so unlike with plain
+/// `LISTEN TBL3`/`NOTIFY TBL3` it is not folded to lowercase and is received as `"TBL3"`.
 #[test]
 fn listen_notify_api() {
     let handle = thread::spawn(|| {
@@ -171,6 +190,7 @@ fn listen_notify_api() {
         conn.listen_loop(Some(1.0), |_i, notify| {
             ControlFlow::Continue(Some(notify.relname()))
         })
+        .expect("Failed to listen for notifications.")
     });
 
     // Give the listener a moment to set up.
@@ -187,13 +207,13 @@ fn listen_notify_api() {
         let res = conn
             .notify("TBL3", None)
             .expect("Failed to execute NOTIFY.");
-        assert_eq!(res.status(), ExecStatusType_PGRES_COMMAND_OK);
+        assert_eq!(res.status(), ExecStatusType_PGRES_TUPLES_OK);
     }
 
     let recvs = handle.join().expect("Thread panicked.");
 
     assert_eq!(recvs.len(), 5);
-    assert_eq!(recvs, vec!["tbl3", "tbl3", "tbl3", "tbl3", "tbl3"]);
+    assert_eq!(recvs, vec!["TBL3", "TBL3", "TBL3", "TBL3", "TBL3"]);
 }
 
 /// ## Test: `catch_notices`
@@ -205,7 +225,7 @@ fn listen_notify_api() {
 ///
 /// - Connects via `PgConn::connect_db_env_vars()` and asserts `ConnStatusType_CONNECTION_OK`.
 /// - Enables libpq tracing to `./test-out/trace.log`.
-/// - Installs a notice processor callback that pushes notice strings into a local `Vec<String>`.
+/// - Installs a notice processor callback that pushes notice strings into a shared `Vec<String>`.
 ///
 /// ### What it executes
 ///
@@ -239,15 +259,19 @@ fn catch_notices() {
 
     assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
 
-    conn.trace("./test-out/trace.log");
+    conn.trace("./test-out/trace.log")
+        .expect("Failed to open the trace file.");
 
-    let mut w = Vec::new();
+    let w = Arc::new(Mutex::new(Vec::new()));
 
-    let _w_pusher = conn.set_notice_processor(|s| w.push(s));
+    {
+        let w = w.clone();
+        conn.set_notice_processor(move |s| w.lock().unwrap().push(s));
+    }
 
     let query = "do $$ begin raise notice 'Hello,'; raise notice 'world!'; end $$; select 1 as one, 2 as two;";
 
-    let mut res = conn.exec(query).expect("Failed to execute query.");
+    let res = conn.exec(query).expect("Failed to execute query.");
 
     res.print(
         "./test-out/res.out",
@@ -258,7 +282,8 @@ fn catch_notices() {
         false,
         false,
         false,
-    );
+    )
+    .expect("Failed to print the result.");
 
     let s =
         fs::read_to_string("./test-out/res.out").expect("Should have been able to read the file");
@@ -270,6 +295,7 @@ fn catch_notices() {
     assert!(res.error_field(PG_DIAG_SEVERITY).is_none());
     assert_eq!(res.cmd_status(), "SELECT 1");
 
+    let w = w.lock().unwrap();
     assert_eq!(w.len(), 2);
     assert_eq!(w[0], "NOTICE:  Hello,\n");
     assert_eq!(w[1], "NOTICE:  world!\n");
@@ -318,3 +344,4255 @@ LINE 1: select * from this_table_does_not_exist;
 
     assert_eq!(res.status(), ExecStatusType_PGRES_FATAL_ERROR);
 }
+
+/// ## Test: `query_as_from_row`
+///
+/// Verifies that rows can be mapped to a user type through `FromRow` and
+/// `PgResult::query_as`, reading columns both by index and by name.
+///
+/// ### Assertions
+///
+/// - `SELECT 1 AS id, 'x' AS label` maps to a single `Item { id: 1, label: "x" }`.
+/// - Asking a row for a missing column yields `PgError::ColumnNotFound`.
+/// - Reading a row out of range yields `PgError::RowNotFound`, whether or not the type
+///   accepts NULL.
+#[test]
+fn query_as_from_row() {
+    #[derive(Debug, PartialEq)]
+    struct Item {
+        id: i32,
+        label: String,
+    }
+
+    impl FromRow for Item {
+        fn from_row(row: &PgRow) -> Result<Self, PgError> {
+            Ok(Item {
+                id: row.get(0)?,
+                label: row.get("label")?,
+            })
+        }
+    }
+
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec("select 1 as id, 'x' as label;")
+        .expect("Failed to execute query.");
+
+    let items = res.query_as::<Item>().expect("Failed to map rows.");
+
+    assert_eq!(
+        items,
+        vec![Item {
+            id: 1,
+            label: "x".to_string()
+        }]
+    );
+
+    assert!(matches!(
+        res.row(0).get::<i32, _>("missing"),
+        Err(PgError::ColumnNotFound(_))
+    ));
+    for row in [-1, 1, 5] {
+        assert!(matches!(res.get::<i32>(row, 0), Err(PgError::RowNotFound(r)) if r == row));
+        assert!(matches!(
+            res.get::<Option<i32>>(row, 0),
+            Err(PgError::RowNotFound(_))
+        ));
+    }
+}
+
+/// ## Test: `get_value_by_name`
+///
+/// Verifies `PgResult::get_value_named` and `PgResult::get_value_raw_named` on
+/// `SELECT 42 AS answer`.
+///
+/// ### Assertions
+///
+/// - `"answer"` parses to `42_i32` and reads raw as `"42"`.
+/// - An unknown column name yields `None` from both accessors.
+#[test]
+fn get_value_by_name() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec("select 42 as answer;")
+        .expect("Failed to execute query.");
+
+    assert_eq!(res.field_number("answer"), Some(0));
+    assert_eq!(res.get_value_named::<i32>(0, "answer"), Some(42));
+    assert_eq!(res.get_value_raw_named(0, "answer"), Some("42".to_string()));
+
+    assert_eq!(res.field_number("question"), None);
+    assert_eq!(res.get_value_named::<i32>(0, "question"), None);
+    assert_eq!(res.get_value_raw_named(0, "question"), None);
+}
+
+/// ## Test: `cmd_tuples_affected_rows`
+///
+/// Verifies that `PgResult::cmd_tuples` parses the affected-row count reported by DML
+/// commands and is `0` for commands that don't report one.
+///
+/// ### Assertions
+///
+/// - `CREATE TEMP TABLE` reports `0`.
+/// - Inserting three rows reports `3`.
+/// - Deleting the two rows with `n > 1` reports `2`.
+#[test]
+fn cmd_tuples_affected_rows() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec("create temp table t (n int);")
+        .expect("Failed to execute query.");
+    assert_eq!(res.status(), ExecStatusType_PGRES_COMMAND_OK);
+    assert_eq!(res.cmd_tuples(), 0);
+
+    let res = conn
+        .exec("insert into t values (1), (2), (3);")
+        .expect("Failed to execute query.");
+    assert_eq!(res.cmd_tuples(), 3);
+
+    let res = conn
+        .exec("delete from t where n > 1;")
+        .expect("Failed to execute query.");
+    assert_eq!(res.cmd_tuples(), 2);
+}
+
+/// ## Test: `oid_value_of_insert`
+///
+/// Verifies `PgResult::oid_value` on a modern table without OIDs, where the inserted row
+/// has no OID and `INSERT ... RETURNING` is the way to get a generated key back.
+///
+/// ### Assertions
+///
+/// - A single-row `INSERT` reports `oid_value() == None`.
+/// - The same insert with `RETURNING id` yields the generated key as a regular value.
+#[test]
+fn oid_value_of_insert() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec("create temp table t (id serial primary key, name text);")
+        .expect("Failed to execute query.");
+    assert_eq!(res.status(), ExecStatusType_PGRES_COMMAND_OK);
+
+    let res = conn
+        .exec("insert into t (name) values ('a');")
+        .expect("Failed to execute query.");
+    assert_eq!(res.status(), ExecStatusType_PGRES_COMMAND_OK);
+    assert_eq!(res.oid_value(), None);
+
+    let res = conn
+        .exec("insert into t (name) values ('b') returning id;")
+        .expect("Failed to execute query.");
+    assert_eq!(res.status(), ExecStatusType_PGRES_TUPLES_OK);
+    assert_eq!(res.oid_value(), None);
+    assert_eq!(res.get_value::<i32>(0, 0), Some(2));
+}
+
+/// ## Test: `describe_prepared_statement`
+///
+/// Verifies `PgConn::describe_prepared` together with `PgResult::nparams` and
+/// `PgResult::param_type` on a statement prepared with two `int4` parameters.
+///
+/// ### Assertions
+///
+/// - The description has status `ExecStatusType_PGRES_COMMAND_OK`.
+/// - `nparams() == 2` and both parameters have type OID `23` (`int4`).
+/// - The statement's single result column is reported by `nfields()`.
+#[test]
+fn describe_prepared_statement() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec("prepare add_ints (int, int) as select $1 + $2 as total;")
+        .expect("Failed to execute PREPARE.");
+    assert_eq!(res.status(), ExecStatusType_PGRES_COMMAND_OK);
+
+    let desc = conn
+        .describe_prepared("add_ints")
+        .expect("Failed to describe prepared statement.");
+
+    assert_eq!(desc.status(), ExecStatusType_PGRES_COMMAND_OK);
+    assert_eq!(desc.nparams(), 2);
+    assert_eq!(desc.param_type(0), 23);
+    assert_eq!(desc.param_type(1), 23);
+    assert_eq!(desc.nfields(), 1);
+    assert_eq!(desc.field_number("total"), Some(0));
+}
+
+/// ## Test: `notice_processor_owned_by_connection`
+///
+/// Verifies that the closure given to `PgConn::set_notice_processor` is owned by the
+/// connection: the caller keeps no handle to it, yet notices raised later are still
+/// delivered, and the closure is dropped together with the connection.
+///
+/// ### What it does
+///
+/// - Installs, from an inner scope, a processor that forwards notices over an `mpsc` channel,
+///   moving the only `Sender` into the closure.
+/// - After the scope has ended, raises a notice and receives it on the channel.
+/// - Drops the connection and asserts the channel is disconnected, i.e. the closure
+///   (and its `Sender`) has been freed.
+///
+/// ### Notes
+///
+/// Before the closure was kept inside `PgConn`, dropping the returned `Box` made the next
+/// notice call into freed memory. To check for that class of bug, run this test under ASan
+/// or Valgrind, e.g. `valgrind --error-exitcode=1 target/debug/deps/integration_test-*`.
+#[test]
+fn notice_processor_owned_by_connection() {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let (tx, rx) = mpsc::channel();
+
+    {
+        conn.set_notice_processor(move |s| tx.send(s).unwrap());
+    }
+
+    let res = conn
+        .exec("do $$ begin raise notice 'still here'; end $$;")
+        .expect("Failed to execute query.");
+    assert_eq!(res.status(), ExecStatusType_PGRES_COMMAND_OK);
+
+    assert_eq!(rx.try_recv(), Ok("NOTICE:  still here\n".to_string()));
+
+    drop(res);
+    drop(conn);
+
+    assert_eq!(rx.try_recv(), Err(mpsc::TryRecvError::Disconnected));
+}
+
+/// ## Test: `reset_notice_processor`
+///
+/// Verifies that `PgConn::reset_notice_processor` restores libpq's default processor and
+/// drops the previously installed closure.
+///
+/// ### What it does
+///
+/// - Installs a processor forwarding notices over an `mpsc` channel and checks that a first
+///   notice is captured.
+/// - Resets the processor, raises a second notice and asserts nothing more is captured and
+///   the channel is disconnected; the second notice goes to `stderr` instead.
+#[test]
+fn reset_notice_processor() {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let (tx, rx) = mpsc::channel();
+
+    conn.set_notice_processor(move |s| tx.send(s).unwrap());
+
+    conn.exec("do $$ begin raise notice 'captured'; end $$;")
+        .expect("Failed to execute query.");
+
+    assert_eq!(rx.try_recv(), Ok("NOTICE:  captured\n".to_string()));
+
+    conn.reset_notice_processor();
+
+    conn.exec("do $$ begin raise notice 'not captured'; end $$;")
+        .expect("Failed to execute query.");
+
+    assert_eq!(rx.try_recv(), Err(mpsc::TryRecvError::Disconnected));
+}
+
+/// ## Test: `exec_file_errors`
+///
+/// Verifies that `PgConn::exec_file` reports failures as `PgError` instead of panicking.
+///
+/// ### Assertions
+///
+/// - A nonexistent path yields `Err(PgError::Io(_))`.
+/// - A file with an embedded nul byte yields `Err(PgError::Nul(_))`.
+/// - A readable file with valid SQL executes and returns its result.
+#[test]
+fn exec_file_errors() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    assert!(matches!(
+        conn.exec_file("./test-out/this_file_does_not_exist.sql"),
+        Err(PgError::Io(_))
+    ));
+
+    let mut nul_file = tempfile::NamedTempFile::new().expect("Failed to create temp file.");
+    nul_file
+        .write_all(b"select 1;\0")
+        .expect("Failed to write temp file.");
+
+    assert!(matches!(
+        conn.exec_file(nul_file.path().to_str().unwrap()),
+        Err(PgError::Nul(_))
+    ));
+
+    let mut sql_file = tempfile::NamedTempFile::new().expect("Failed to create temp file.");
+    sql_file
+        .write_all(b"select 7 as seven;")
+        .expect("Failed to write temp file.");
+
+    let res = conn
+        .exec_file(sql_file.path().to_str().unwrap())
+        .expect("Failed to execute file.");
+
+    assert_eq!(res.status(), ExecStatusType_PGRES_TUPLES_OK);
+    assert_eq!(res.get_value::<i32>(0, 0), Some(7));
+}
+
+/// ## Test: `display_in_memory`
+///
+/// Verifies that `Display for PgResult` renders the aligned table in memory, in the same
+/// layout as `PgResult::print`.
+///
+/// ### Assertions
+///
+/// - `SELECT 1 AS one` renders as the expected bordered table with a `(1 row)` footer.
+/// - For a result mixing numbers, text, NULLs and empty strings, `to_string()` matches the
+///   output of `print(...)` written to `./test-out/display.out`.
+/// - A command without columns renders as the empty string.
+#[test]
+fn display_in_memory() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec("select 1 as one;")
+        .expect("Failed to execute query.");
+
+    assert_eq!(
+        format!("{}", res),
+        "+-----+
+| one |
++-----+
+|   1 |
++-----+
+(1 row)
+
+"
+    );
+
+    let res = conn
+        .exec(
+            "select 1 as a, 'hello world' as b, null as c, -3.5 as d, '' as e \
+             union all select 100, 'x', 'y', 2, 'abc';",
+        )
+        .expect("Failed to execute query.");
+
+    res.print(
+        "./test-out/display.out",
+        true,
+        true,
+        "|",
+        true,
+        false,
+        false,
+        false,
+    )
+    .expect("Failed to print the result.");
+
+    let s = fs::read_to_string("./test-out/display.out")
+        .expect("Should have been able to read the file");
+
+    assert_eq!(res.to_string(), s);
+
+    let res = conn
+        .exec("create temp table t ();")
+        .expect("Failed to execute query.");
+
+    assert_eq!(res.to_string(), "");
+}
+
+/// ## Test: `field_type_oids`
+///
+/// Verifies `PgResult::field_type` and the `type_name` lookup on a few well-known types.
+///
+/// ### Assertions
+///
+/// - `SELECT 1::int4, 'x'::text, true, '{}'::json` reports OIDs `23`, `25`, `16` and `114`.
+/// - `type_name` maps them to `"int4"`, `"text"`, `"bool"` and `"json"`, and an unknown OID to `None`.
+#[test]
+fn field_type_oids() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec("select 1::int4, 'x'::text, true, '{}'::json;")
+        .expect("Failed to execute query.");
+
+    assert_eq!(res.field_type(0), 23);
+    assert_eq!(res.field_type(0), INT4OID);
+    assert_eq!(res.field_type(1), TEXTOID);
+    assert_eq!(res.field_type(2), BOOLOID);
+    assert_eq!(res.field_type(3), JSONOID);
+
+    let names: Vec<_> = (0..res.nfields())
+        .map(|col| type_name(res.field_type(col)))
+        .collect();
+
+    assert_eq!(
+        names,
+        vec![Some("int4"), Some("text"), Some("bool"), Some("json")]
+    );
+    assert_eq!(type_name(0), None);
+}
+
+/// ## Test: `field_modifier_and_size`
+///
+/// Verifies `PgResult::field_modifier` and `PgResult::field_size`.
+///
+/// ### Assertions
+///
+/// - `'x'::varchar(10)` has modifier `14` (length `10` plus the 4-byte header) and a
+///   negative (variable) size.
+/// - `1::int4` has no modifier (`-1`) and size `4`.
+#[test]
+fn field_modifier_and_size() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec("select 'x'::varchar(10), 1::int4;")
+        .expect("Failed to execute query.");
+
+    assert_eq!(res.field_modifier(0), 14);
+    assert!(res.field_size(0) < 0);
+
+    assert_eq!(res.field_modifier(1), -1);
+    assert_eq!(res.field_size(1), 4);
+}
+
+/// ## Test: `field_source_table`
+///
+/// Verifies `PgResult::field_table` and `PgResult::field_table_col` for a real table column
+/// and for a computed one.
+///
+/// ### Assertions
+///
+/// - Column `b` of a temp table `t (a int, b text)` comes from `'t'::regclass` at position `2`.
+/// - The computed column `a + 1` reports `INVALID_OID` and `0`.
+#[test]
+fn field_source_table() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.exec("create temp table t (a int, b text); insert into t values (1, 'x');")
+        .expect("Failed to execute query.");
+
+    let res = conn
+        .exec("select 't'::regclass::oid;")
+        .expect("Failed to execute query.");
+    let table_oid = res
+        .get_value::<u32>(0, 0)
+        .expect("Failed to read table OID.");
+
+    let res = conn
+        .exec("select b, a + 1 from t;")
+        .expect("Failed to execute query.");
+
+    assert_ne!(res.field_table(0), INVALID_OID);
+    assert_eq!(res.field_table(0), table_oid);
+    assert_eq!(res.field_table_col(0), 2);
+
+    assert_eq!(res.field_table(1), INVALID_OID);
+    assert_eq!(res.field_table_col(1), 0);
+}
+
+/// ## Test: `field_format_text_and_binary`
+///
+/// Verifies `PgResult::field_format` for text and binary results. A binary result is
+/// obtained without parameters by fetching from a `BINARY CURSOR`.
+///
+/// ### Assertions
+///
+/// - A plain `SELECT 1::int4` reports format `0` (text).
+/// - Fetching the same value from a binary cursor reports format `1`.
+#[test]
+fn field_format_text_and_binary() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec("select 1::int4;")
+        .expect("Failed to execute query.");
+    assert_eq!(res.field_format(0), 0);
+
+    let res = conn
+        .exec("begin; declare c binary cursor for select 1::int4;")
+        .expect("Failed to execute query.");
+    assert_eq!(res.status(), ExecStatusType_PGRES_COMMAND_OK);
+
+    let res = conn
+        .exec("fetch 1 from c;")
+        .expect("Failed to execute query.");
+    assert_eq!(res.status(), ExecStatusType_PGRES_TUPLES_OK);
+    assert_eq!(res.field_format(0), 1);
+
+    conn.exec("rollback;").expect("Failed to execute query.");
+}
+
+/// ## Test: `verbose_error_message`
+///
+/// Verifies `PgResult::verbose_error_message` on a unique-constraint violation.
+///
+/// ### Assertions
+///
+/// - `error_message()` doesn't mention the SQLSTATE, while with `Verbosity::Verbose` the
+///   message starts with `23505` and includes the `DETAIL` line.
+/// - With `Verbosity::Terse` the message is a single line.
+#[test]
+fn verbose_error_message() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.exec("create temp table t (id int primary key); insert into t values (1);")
+        .expect("Failed to execute query.");
+
+    let res = conn
+        .exec("insert into t values (1);")
+        .expect("Failed to execute query.");
+    assert_eq!(res.status(), ExecStatusType_PGRES_FATAL_ERROR);
+    assert!(!res.error_message().contains("23505"));
+
+    let verbose = res.verbose_error_message(Verbosity::Verbose, ContextVisibility::Always);
+    assert!(verbose.starts_with("ERROR:  23505: duplicate key value"));
+    assert!(verbose.contains("DETAIL:  Key (id)=(1) already exists."));
+
+    let terse = res.verbose_error_message(Verbosity::Terse, ContextVisibility::Never);
+    assert_eq!(terse.lines().count(), 1);
+}
+
+/// ## Test: `unique_violation_diagnostics`
+///
+/// Verifies `PgResult::sqlstate` and `PgResult::diagnostics` on a unique-constraint violation.
+///
+/// ### Assertions
+///
+/// - `sqlstate() == Some("23505")`.
+/// - `diagnostics()` carries severity, primary message, detail, constraint and table names,
+///   and no hint or column name.
+/// - A successful result has no diagnostics at all.
+#[test]
+fn unique_violation_diagnostics() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.exec(
+        "create temp table t (id int constraint t_pk primary key); insert into t values (1);",
+    )
+    .expect("Failed to execute query.");
+
+    let res = conn
+        .exec("insert into t values (1);")
+        .expect("Failed to execute query.");
+
+    assert_eq!(res.sqlstate(), Some("23505".to_string()));
+
+    let diag = res.diagnostics();
+    assert_eq!(diag.severity.as_deref(), Some("ERROR"));
+    assert_eq!(diag.sqlstate.as_deref(), Some("23505"));
+    assert_eq!(
+        diag.message_primary.as_deref(),
+        Some("duplicate key value violates unique constraint \"t_pk\"")
+    );
+    assert_eq!(diag.detail.as_deref(), Some("Key (id)=(1) already exists."));
+    assert_eq!(diag.hint, None);
+    assert_eq!(diag.column_name, None);
+    assert_eq!(diag.constraint_name.as_deref(), Some("t_pk"));
+    assert_eq!(diag.table_name.as_deref(), Some("t"));
+
+    let res = conn.exec("select 1;").expect("Failed to execute query.");
+    assert_eq!(res.sqlstate(), None);
+    assert_eq!(res.diagnostics(), Diagnostics::default());
+}
+
+/// ## Test: `check_result_status`
+///
+/// Verifies that `PgResult::check` maps result statuses to `Result`.
+///
+/// ### Assertions
+///
+/// - `SELECT 1` passes through as `Ok`, still readable.
+/// - A syntax error yields `PgError::Query` with status `PGRES_FATAL_ERROR`, the server's
+///   message and SQLSTATE `42601`.
+/// - An empty query yields `PgError::Query` with status `PGRES_EMPTY_QUERY`.
+#[test]
+fn check_result_status() -> Result<(), PgError> {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn.exec("select 1;")?.check()?;
+    assert_eq!(res.get_value::<i32>(0, 0), Some(1));
+
+    match conn.exec("selec 1;")?.check() {
+        Err(PgError::Query {
+            status,
+            message,
+            sqlstate,
+        }) => {
+            assert_eq!(status, ExecStatusType_PGRES_FATAL_ERROR);
+            assert!(message.starts_with("ERROR:  syntax error at or near \"selec\""));
+            assert_eq!(sqlstate.as_deref(), Some("42601"));
+        }
+        _ => panic!("Expected a query error."),
+    }
+
+    match conn.exec("")?.check() {
+        Err(e @ PgError::Query { status, .. }) => {
+            assert_eq!(status, ExecStatusType_PGRES_EMPTY_QUERY);
+            assert_eq!(e.to_string(), "Query failed: PGRES_EMPTY_QUERY");
+        }
+        _ => panic!("Expected a query error."),
+    }
+
+    Ok(())
+}
+
+/// ## Test: `status_enums`
+///
+/// Verifies the `ConnStatus`/`ExecStatus` enums returned by `status_enum()` and their
+/// conversions from and to the raw `ConnStatusType`/`ExecStatusType` values.
+///
+/// ### Assertions
+///
+/// - A healthy connection reports `ConnStatus::Ok`.
+/// - `SELECT 1` matches `ExecStatus::TuplesOk`, a DDL statement `ExecStatus::CommandOk` and
+///   an invalid query `ExecStatus::FatalError`.
+/// - Unrecognized raw values map to `Unknown` and back unchanged.
+#[test]
+fn status_enums() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status_enum(), ConnStatus::Ok);
+
+    let res = conn.exec("select 1;").expect("Failed to execute query.");
+    match res.status_enum() {
+        ExecStatus::TuplesOk => {}
+        other => panic!("Unexpected status {:?}.", other),
+    }
+
+    let res = conn
+        .exec("create temp table t ();")
+        .expect("Failed to execute query.");
+    assert_eq!(res.status_enum(), ExecStatus::CommandOk);
+
+    let res = conn.exec("selec 1;").expect("Failed to execute query.");
+    assert_eq!(res.status_enum(), ExecStatus::FatalError);
+
+    assert_eq!(
+        ExecStatus::from(ExecStatusType_PGRES_TUPLES_OK),
+        ExecStatus::TuplesOk
+    );
+    assert_eq!(ExecStatus::from(1000), ExecStatus::Unknown(1000));
+    assert_eq!(ExecStatusType::from(ExecStatus::Unknown(1000)), 1000);
+    assert_eq!(
+        ConnStatusType::from(ConnStatus::Ok),
+        ConnStatusType_CONNECTION_OK
+    );
+}
+
+/// ## Test: `listen_many_unlisten`
+///
+/// Verifies `PgConn::listen_many`, `PgConn::unlisten` and `PgConn::unlisten_all`, using
+/// channel names that need quoting. A session receives its own notifications, so a single
+/// connection both listens and notifies; they are queued by the time `exec` returns.
+///
+/// ### Assertions
+///
+/// - After listening on `"Mixed Case"` and `plain`, notifying both delivers both, with
+///   `"Mixed Case"` keeping its case.
+/// - After `unlisten("plain")` only `"Mixed Case"` is delivered.
+/// - After `unlisten_all()` nothing is delivered.
+#[test]
+fn listen_many_unlisten() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let channels = ["Mixed Case", "plain"];
+
+    let notify_all = |conn: &mut PgConn| -> Result<Vec<String>, PgError> {
+        for channel in channels {
+            let query = format!("NOTIFY {};", conn.escape_identifier(channel)?);
+            conn.exec(&query)?.check()?;
+        }
+        let mut recvs = Vec::new();
+        while let Some(notify) = conn.notifies() {
+            recvs.push(notify.relname());
+        }
+        Ok(recvs)
+    };
+
+    conn.listen_many(&channels)?;
+    assert_eq!(notify_all(&mut conn)?, vec!["Mixed Case", "plain"]);
+
+    conn.unlisten("plain")?;
+    assert_eq!(notify_all(&mut conn)?, vec!["Mixed Case"]);
+
+    conn.unlisten_all()?;
+    assert!(notify_all(&mut conn)?.is_empty());
+
+    Ok(())
+}
+
+/// ## Test: `notify_payload_escaping`
+///
+/// Verifies that `PgConn::notify` binds channel and payload as parameters, so payloads with
+/// quotes, newlines and SQL fragments arrive unchanged, and that `listen`/`notify` agree on the
+/// verbatim channel name.
+///
+/// ### Assertions
+///
+/// - Each payload sent on channel `It's "odd"` is received byte-for-byte from `PgNotify::extra`.
+/// - A `None` payload is received as `""`.
+#[test]
+fn notify_payload_escaping() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let channel = "It's \"odd\"";
+    conn.listen(channel)?.check()?;
+
+    let payloads = [
+        "it's a 'quoted' payload",
+        "multi\nline\n",
+        "'); drop table pg_class; --",
+    ];
+
+    for payload in payloads {
+        conn.notify(channel, Some(payload))?.check()?;
+
+        let notify = conn.notifies().expect("Expected a notification.");
+        assert_eq!(notify.relname(), channel);
+        assert_eq!(notify.extra(), payload);
+    }
+
+    conn.notify(channel, None)?.check()?;
+    let notify = conn.notifies().expect("Expected a notification.");
+    assert_eq!(notify.extra(), "");
+
+    Ok(())
+}
+
+/// ## Test: `listen_loop_connection_lost`
+///
+/// Verifies that `PgConn::listen_loop` reports a lost connection as an error instead of a
+/// clean exit.
+///
+/// ### What it does
+///
+/// - A listener thread connects, sends its backend PID to the main thread, listens on
+///   `lost` and enters `listen_loop` with a generous `10s` timeout.
+/// - The main thread terminates that backend with `pg_terminate_backend`.
+///
+/// ### Assertions
+///
+/// - `listen_loop` returns `Err(PgError::Connection(_))` well before the timeout.
+#[test]
+fn listen_loop_connection_lost() {
+    let (tx, rx) = mpsc::channel();
+
+    let handle = thread::spawn(move || {
+        let mut conn =
+            PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+        assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+        let res = conn
+            .exec("select pg_backend_pid();")
+            .expect("Failed to execute query.");
+        tx.send(res.get_value::<i32>(0, 0).unwrap()).unwrap();
+
+        conn.listen("lost").expect("Failed to execute LISTEN.");
+
+        let started = std::time::Instant::now();
+        let res = conn.listen_loop(Some(10.0), |_i, notify| {
+            ControlFlow::Continue(Some(notify.relname()))
+        });
+        (res, started.elapsed())
+    });
+
+    let pid = rx.recv().expect("Listener thread didn't report its PID.");
+
+    // Give the listener a moment to enter the loop.
+    thread::sleep(std::time::Duration::from_millis(100));
+
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    let res = conn
+        .exec_params("select pg_terminate_backend($1::int);", &[&pid])
+        .expect("Failed to execute query.");
+    assert_eq!(res.get_value_raw(0, 0), "t");
+
+    let (res, elapsed) = handle.join().expect("Thread panicked.");
+
+    assert!(matches!(res, Err(PgError::Connection(_))));
+    assert!(elapsed < std::time::Duration::from_secs(10));
+}
+
+/// ## Test: `listen_loop_until_stopped`
+///
+/// Verifies that `PgConn::listen_loop_until` keeps waiting across idle poll intervals and
+/// exits once another thread sets the stop flag.
+///
+/// ### What it does
+///
+/// - A listener thread listens on `stoppable` and runs `listen_loop_until` with a `100ms`
+///   poll interval, forwarding each payload to the main thread as well as collecting it.
+/// - The main thread sends three notifications, waits until all three were seen, lets a few
+///   idle intervals pass, then sets the flag.
+///
+/// ### Assertions
+///
+/// - The loop returns `Ok` with exactly the three payloads, in order.
+#[test]
+fn listen_loop_until_stopped() {
+    let stop = Arc::new(AtomicBool::new(false));
+    let (tx, rx) = mpsc::channel();
+
+    let handle = {
+        let stop = stop.clone();
+        thread::spawn(move || {
+            let mut conn = PgConn::connect_db_env_vars()
+                .expect("Failed to create PGconn from connection string.");
+
+            assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+            conn.listen("stoppable").expect("Failed to execute LISTEN.");
+            tx.send(None).unwrap();
+
+            conn.listen_loop_until(0.1, &stop, |_i, notify| {
+                tx.send(Some(notify.extra())).unwrap();
+                ControlFlow::Continue(Some(notify.extra()))
+            })
+        })
+    };
+
+    // Wait for the listener to subscribe.
+    assert_eq!(rx.recv().unwrap(), None);
+
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    for payload in ["one", "two", "three"] {
+        conn.notify("stoppable", Some(payload))
+            .expect("Failed to execute NOTIFY.");
+    }
+
+    for _ in 0..3 {
+        assert!(rx.recv().unwrap().is_some());
+    }
+
+    thread::sleep(std::time::Duration::from_millis(300));
+    stop.store(true, Ordering::Release);
+
+    let recvs = handle
+        .join()
+        .expect("Thread panicked.")
+        .expect("Failed to listen for notifications.");
+
+    assert_eq!(recvs, vec!["one", "two", "three"]);
+}
+
+/// ## Test: `socket_as_raw_fd`
+///
+/// Verifies that `PgSocket` exposes libpq's descriptor through `AsRawFd` and `AsFd`.
+///
+/// ### Assertions
+///
+/// - `as_raw_fd()` is a valid descriptor, stable across `socket()` calls and equal to
+///   `as_fd().as_raw_fd()`.
+/// - A duplicate of the descriptor, wrapped as a `TcpStream`, is connected to the server's
+///   port (`PGPORT`); the duplicate is closed, never libpq's own descriptor.
+#[test]
+fn socket_as_raw_fd() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let socket = conn.socket();
+    let fd = socket.as_raw_fd();
+
+    assert!(fd >= 0);
+    assert_eq!(conn.socket().as_raw_fd(), fd);
+    assert_eq!(socket.as_fd().as_raw_fd(), fd);
+
+    let owned = socket
+        .as_fd()
+        .try_clone_to_owned()
+        .expect("Failed to duplicate the descriptor.");
+    let stream = std::net::TcpStream::from(owned);
+    let port: u16 = std::env::var("PGPORT")
+        .expect("PGPORT should be set.")
+        .parse()
+        .unwrap();
+
+    assert_eq!(stream.peer_addr().unwrap().port(), port);
+}
+
+/// ## Test: `poll_duration_timeout`
+///
+/// Verifies `PgSocket::poll_duration` against an idle connection.
+///
+/// ### Assertions
+///
+/// - Polling for readability with `Duration::from_millis(500)` reports
+///   `PgSocketPollResult::Timeout` after roughly that long.
+/// - Polling for writability succeeds immediately.
+#[test]
+fn poll_duration_timeout() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let timeout = Duration::from_millis(500);
+    let started = Instant::now();
+
+    assert!(matches!(
+        conn.socket().poll_duration(true, false, Some(timeout)),
+        Err(PgSocketPollResult::Timeout)
+    ));
+
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_millis(450));
+    assert!(elapsed < Duration::from_secs(5));
+
+    assert!(
+        conn.socket()
+            .poll_duration(false, true, Some(timeout))
+            .is_ok()
+    );
+}
+
+/// ## Test: `async_query_loop`
+///
+/// Drives a query through the non-blocking sequence documented on `PgConn::is_busy`:
+/// `send_query`, then poll → `consume_input` while `is_busy()`, then `get_result` until `None`.
+///
+/// ### Assertions
+///
+/// - The connection is busy right after sending `SELECT pg_sleep(0.2), 1`.
+/// - At least one poll is needed before the result is ready.
+/// - `get_result` returns one `PGRES_TUPLES_OK` result with value `1`, then `None`.
+#[test]
+fn async_query_loop() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.send_query("select pg_sleep(0.2), 1 as one;")?;
+    assert!(conn.is_busy());
+
+    let mut polls = 0;
+    while conn.is_busy() {
+        assert!(
+            conn.socket()
+                .poll_duration(true, false, Some(Duration::from_secs(5)))
+                .is_ok()
+        );
+        conn.consume_input().map_err(PgError::Connection)?;
+        polls += 1;
+    }
+    assert!(polls >= 1);
+
+    let res = conn.get_result().expect("Expected a result.").check()?;
+    assert_eq!(res.get_value_named::<i32>(0, "one"), Some(1));
+
+    assert!(conn.get_result().is_none());
+
+    Ok(())
+}
+
+/// ## Test: `trace_to_fd`
+///
+/// Verifies that `PgConn::trace_fd` writes the protocol trace to a caller-owned descriptor
+/// and that `untrace` flushes it there without closing the caller's descriptor.
+///
+/// ### Assertions
+///
+/// - After tracing `select 4242;` into a temp file and calling `untrace`, the file contains the
+///   frontend `Query` message with that text and the backend `DataRow`.
+/// - The caller's file handle is still writable afterwards.
+#[test]
+fn trace_to_fd() {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let mut file = tempfile::tempfile().expect("Failed to create temp file.");
+
+    conn.trace_fd(&file)
+        .expect("Failed to trace to descriptor.");
+    conn.exec("select 4242;").expect("Failed to execute query.");
+    conn.untrace();
+
+    let mut log = String::new();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.read_to_string(&mut log).unwrap();
+
+    assert!(log.contains("Query\t \"select 4242;\""));
+    assert!(log.contains("DataRow"));
+
+    file.write_all(b"still open\n")
+        .expect("The caller's descriptor should still be open.");
+}
+
+/// ## Test: `trace_without_timestamps`
+///
+/// Verifies that `PgConn::set_trace_flags(true, false)` removes the timestamp that otherwise
+/// starts every trace line.
+///
+/// ### Assertions
+///
+/// - With default flags every line starts with a digit (the timestamp).
+/// - With timestamps suppressed every line starts with the direction `F` or `B`.
+#[test]
+fn trace_without_timestamps() {
+    let trace_lines = |suppress_timestamps: bool| -> Vec<String> {
+        let mut conn =
+            PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+        assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+        let mut file = tempfile::tempfile().expect("Failed to create temp file.");
+
+        conn.trace_fd(&file)
+            .expect("Failed to trace to descriptor.");
+        conn.set_trace_flags(suppress_timestamps, false);
+        conn.exec("select 1;").expect("Failed to execute query.");
+        conn.untrace();
+
+        let mut log = String::new();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.read_to_string(&mut log).unwrap();
+        log.lines().map(String::from).collect()
+    };
+
+    let lines = trace_lines(false);
+    assert!(!lines.is_empty());
+    assert!(
+        lines
+            .iter()
+            .all(|l| l.starts_with(|c: char| c.is_ascii_digit()))
+    );
+
+    let lines = trace_lines(true);
+    assert!(!lines.is_empty());
+    assert!(
+        lines
+            .iter()
+            .all(|l| l.starts_with("F\t") || l.starts_with("B\t"))
+    );
+}
+
+/// ## Test: `clone_result_outlives_original`
+///
+/// Verifies that `Clone for PgResult` makes an independent copy of columns and rows.
+///
+/// ### Assertions
+///
+/// - After dropping the original result, the clone still has 2 rows and 2 columns with the
+///   same names and values, including the NULL.
+#[test]
+fn clone_result_outlives_original() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec("select 1 as id, 'a' as name union all select 2, null;")
+        .expect("Failed to execute query.");
+
+    let copy = res.clone();
+    drop(res);
+
+    assert_eq!(copy.status(), ExecStatusType_PGRES_TUPLES_OK);
+    assert_eq!(copy.ntuples(), 2);
+    assert_eq!(copy.nfields(), 2);
+    assert_eq!(copy.field_name(1).as_deref(), Some("name"));
+    assert_eq!(copy.get_value::<i32>(1, 0), Some(2));
+    assert_eq!(copy.get_value_raw(0, 1), "a");
+    assert!(copy.is_null(1, 1));
+}
+
+/// ## Test: `synthetic_result`
+///
+/// Builds a result without a server via `make_empty`, `set_attrs` and `set_value`.
+///
+/// ### Assertions
+///
+/// - The result has the requested status, 2 columns with the given names and types, and 1 row.
+/// - Values read back with `get_value_raw`, and the NULL cell is reported by `is_null`.
+/// - Setting a value outside the columns fails.
+#[test]
+fn synthetic_result() {
+    let mut res = PgResult::make_empty(ExecStatusType_PGRES_TUPLES_OK);
+
+    assert!(
+        res.set_attrs(&[("id", INT4OID), ("name", TEXTOID)])
+            .expect("Column names contain no NUL.")
+    );
+    assert!(res.set_value(0, 0, Some("42")));
+    assert!(res.set_value(0, 1, None));
+    assert!(!res.set_value(0, 2, Some("x")));
+
+    assert_eq!(res.status(), ExecStatusType_PGRES_TUPLES_OK);
+    assert_eq!(res.nfields(), 2);
+    assert_eq!(res.ntuples(), 1);
+    assert_eq!(res.field_name(1).as_deref(), Some("name"));
+    assert_eq!(res.field_type(0), INT4OID);
+    assert_eq!(res.get_value_raw(0, 0), "42");
+    assert_eq!(res.get_value::<i32>(0, 0), Some(42));
+    assert!(res.is_null(0, 1));
+}
+
+/// ## Test: `result_memory_size`
+///
+/// Compares the memory footprint reported by `memory_size` for a small and a large result.
+///
+/// ### Assertions
+///
+/// - A 10000-row result reports more bytes than a single-row one, which reports a non-zero size.
+#[test]
+fn result_memory_size() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let small = conn.exec("select 1;").expect("Failed to execute query.");
+    let large = conn
+        .exec("select i, repeat('x', 100) from generate_series(1, 10000) as i;")
+        .expect("Failed to execute query.");
+
+    assert!(small.memory_size() > 0);
+    assert!(large.memory_size() > small.memory_size());
+}
+
+/// ## Test: `get_bool_values`
+///
+/// Decodes boolean columns, which Postgres returns as `t`/`f`, with `get_bool`.
+///
+/// ### Assertions
+///
+/// - `true` and `false` decode to `Some(true)` and `Some(false)`.
+/// - A NULL boolean and a non-boolean text decode to `None`.
+#[test]
+fn get_bool_values() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec("select true, false, null::boolean, 'yes';")
+        .expect("Failed to execute query.");
+
+    assert_eq!(res.get_value_raw(0, 0), "t");
+    assert_eq!(res.get_bool(0, 0), Some(true));
+    assert_eq!(res.get_bool(0, 1), Some(false));
+    assert_eq!(res.get_bool(0, 2), None);
+    assert_eq!(res.get_bool(0, 3), None);
+}
+
+/// ## Test: `to_sql_from_sql_round_trip`
+///
+/// Round-trips typed values through `exec_params` parameters and decodes them back with
+/// `PgResult::get`.
+///
+/// ### Assertions
+///
+/// - An `i64`, a `bool` and a `String` come back with their Postgres types and values.
+/// - A `None` parameter is NULL: it decodes as `None` into an `Option` and as
+///   `PgError::UnexpectedNull` otherwise.
+/// - Decoding text as `i64` fails with `PgError::InvalidValue`.
+#[test]
+fn to_sql_from_sql_round_trip() -> Result<(), PgError> {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let name = "hello, world".to_string();
+    let missing: Option<i32> = None;
+    let res = conn
+        .exec_params(
+            "select $1, $2, $3, $4;",
+            &[&9_000_000_000_i64, &true, &name, &missing],
+        )?
+        .check()?;
+
+    assert_eq!(res.field_type(0), INT8OID);
+    assert_eq!(res.field_type(1), BOOLOID);
+    assert_eq!(res.get::<i64>(0, 0)?, 9_000_000_000);
+    assert!(res.get::<bool>(0, 1)?);
+    assert_eq!(res.get::<String>(0, 2)?, name);
+    assert_eq!(res.get::<Option<i32>>(0, 3)?, None);
+    assert!(matches!(
+        res.get::<i32>(0, 3),
+        Err(PgError::UnexpectedNull { row: 0, col: 3 })
+    ));
+    assert!(matches!(
+        res.get::<i64>(0, 2),
+        Err(PgError::InvalidValue { .. })
+    ));
+
+    Ok(())
+}
+
+/// ## Test: `get_json_values`
+///
+/// Parses `jsonb` and `json` columns as `serde_json::Value` with `get_json`.
+///
+/// ### Assertions
+///
+/// - `'{"a":1}'::jsonb` parses to the object `{"a": 1}`.
+/// - SQL NULL is `Value::Null`, while malformed JSON in a text column is an error.
+#[cfg(feature = "json")]
+#[test]
+fn get_json_values() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec(r#"select '{"a":1}'::jsonb, '[1, "x"]'::json, null::jsonb, '{"a":'::text;"#)
+        .expect("Failed to execute query.");
+
+    let value = res.get_json(0, 0).expect("Failed to parse JSON.");
+    assert_eq!(value, serde_json::json!({"a": 1}));
+    assert_eq!(value["a"], 1);
+    assert_eq!(
+        res.get_json(0, 1).expect("Failed to parse JSON."),
+        serde_json::json!([1, "x"])
+    );
+    assert_eq!(
+        res.get_json(0, 2).expect("Failed to parse JSON."),
+        serde_json::Value::Null
+    );
+    assert!(matches!(
+        res.get_json(0, 3),
+        Err(PgError::InvalidValue { row: 0, col: 3, .. })
+    ));
+}
+
+/// ## Test: `get_array_values`
+///
+/// Parses one-dimensional Postgres arrays from their text format with `get_array` and
+/// `get_array_opt`.
+///
+/// ### Assertions
+///
+/// - `ARRAY[1,2,3]` parses to `vec![1, 2, 3]`, and an empty array to an empty vector.
+/// - Quoted text elements keep commas, escaped quotes and backslashes, and a quoted `"NULL"`
+///   stays a string.
+/// - NULL elements are `None` with `get_array_opt`, and make `get_array` return `None`.
+/// - A two-dimensional array, a NULL array and a non-parsable element give `None`.
+#[test]
+fn get_array_values() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec(
+            r#"select ARRAY[1,2,3], ARRAY['a,b', 'say "hi"', 'back\slash', 'NULL', ''],
+                    ARRAY[1, NULL, 3], '{}'::int[], ARRAY[[1,2],[3,4]], null::int[],
+                    ARRAY['x'];"#,
+        )
+        .expect("Failed to execute query.");
+
+    assert_eq!(res.get_array::<i32>(0, 0), Some(vec![1, 2, 3]));
+    assert_eq!(
+        res.get_array::<String>(0, 1),
+        Some(vec![
+            "a,b".to_string(),
+            r#"say "hi""#.to_string(),
+            r"back\slash".to_string(),
+            "NULL".to_string(),
+            "".to_string(),
+        ])
+    );
+    assert_eq!(
+        res.get_array_opt::<i32>(0, 2),
+        Some(vec![Some(1), None, Some(3)])
+    );
+    assert_eq!(res.get_array::<i32>(0, 2), None);
+    assert_eq!(res.get_array::<i32>(0, 3), Some(vec![]));
+    assert_eq!(res.get_array::<i32>(0, 4), None);
+    assert_eq!(res.get_array::<i32>(0, 5), None);
+    assert_eq!(res.get_array::<i32>(0, 6), None);
+}
+
+/// ## Test: `result_to_csv`
+///
+/// Writes a result as CSV into a `Vec<u8>` with `to_csv`.
+///
+/// ### Assertions
+///
+/// - The header holds the column names, and fields with the delimiter, quotes or newlines
+///   are quoted with doubled quotes.
+/// - NULL is an empty field, while the empty string is `""`.
+/// - Without header and with `;` as delimiter, a comma no longer needs quoting.
+/// - A non-ASCII delimiter byte is rejected as invalid input, writing nothing.
+#[test]
+fn result_to_csv() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec(
+            r#"select 1 as a, 'x,y' as b, 'say "hi"' as c, E'two\nlines' as d, null as e, '' as f;"#,
+        )
+        .expect("Failed to execute query.");
+
+    let mut out = Vec::new();
+    res.to_csv(&mut out, true, b',')
+        .expect("Failed to write CSV.");
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "a,b,c,d,e,f\n1,\"x,y\",\"say \"\"hi\"\"\",\"two\nlines\",,\"\"\n"
+    );
+
+    let mut out = Vec::new();
+    res.to_csv(&mut out, false, b';')
+        .expect("Failed to write CSV.");
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "1;x,y;\"say \"\"hi\"\"\";\"two\nlines\";;\"\"\n"
+    );
+
+    let mut out = Vec::new();
+    let err = res.to_csv(&mut out, true, 0xa7).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(out.is_empty());
+}
+
+/// ## Test: `result_to_json`
+///
+/// Serializes a result with `to_json` and parses it back with `serde_json`.
+///
+/// ### Assertions
+///
+/// - The output is an array of one object per row, keyed by column name.
+/// - Integers, numerics and booleans are JSON numbers and booleans, NULL is `null`, `jsonb`
+///   is embedded, and text (with quotes and newlines) and `NaN` are strings.
+#[test]
+fn result_to_json() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec(
+            r#"select 1 as id, 'say "hi"' as name, true as ok, 2.50 as price,
+                      '{"k":[1]}'::jsonb as doc, 'NaN'::float8 as nan
+               union all
+               select 2, E'two\nlines', false, null, null, 1.5;"#,
+        )
+        .expect("Failed to execute query.");
+
+    let mut out = Vec::new();
+    res.to_json(&mut out).expect("Failed to write JSON.");
+    let value: serde_json::Value = serde_json::from_slice(&out).expect("Invalid JSON.");
+
+    assert_eq!(
+        value,
+        serde_json::json!([
+            {"id": 1, "name": "say \"hi\"", "ok": true, "price": 2.50, "doc": {"k": [1]}, "nan": "NaN"},
+            {"id": 2, "name": "two\nlines", "ok": false, "price": null, "doc": null, "nan": 1.5},
+        ])
+    );
+}
+
+/// ## Test: `query_one_opt_scalar`
+///
+/// Exercises the `query_one`, `query_opt` and `query_scalar` shortcuts over
+/// `generate_series`.
+///
+/// ### Assertions
+///
+/// - `query_scalar::<i64>` returns `count(*)`, and reports an empty result as
+///   `PgError::UnexpectedRowCount(0)`.
+/// - `query_one` maps the single row, and fails with `UnexpectedRowCount` on zero or three rows.
+/// - `query_opt` gives `None` for zero rows and the mapped row for one.
+/// - A failing query surfaces as `PgError::Query`.
+#[test]
+fn query_one_opt_scalar() -> Result<(), PgError> {
+    #[derive(Debug, PartialEq)]
+    struct Pair {
+        n: i32,
+        square: i32,
+    }
+
+    impl FromRow for Pair {
+        fn from_row(row: &PgRow) -> Result<Self, PgError> {
+            Ok(Pair {
+                n: row.get("n")?,
+                square: row.get("square")?,
+            })
+        }
+    }
+
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let count: i64 =
+        conn.query_scalar("select count(*) from generate_series(1, $1) as n;", &[&10])?;
+    assert_eq!(count, 10);
+    assert!(matches!(
+        conn.query_scalar::<i64>("select 1 where false;", &[]),
+        Err(PgError::UnexpectedRowCount(0))
+    ));
+
+    let query = "select n, n * n as square from generate_series(1, $1) as n where n >= $2;";
+
+    assert_eq!(
+        conn.query_one::<Pair>(query, &[&3, &3])?,
+        Pair { n: 3, square: 9 }
+    );
+    assert!(matches!(
+        conn.query_one::<Pair>(query, &[&3, &4]),
+        Err(PgError::UnexpectedRowCount(0))
+    ));
+    assert!(matches!(
+        conn.query_one::<Pair>(query, &[&3, &1]),
+        Err(PgError::UnexpectedRowCount(3))
+    ));
+
+    assert_eq!(conn.query_opt::<Pair>(query, &[&3, &4])?, None);
+    assert_eq!(
+        conn.query_opt::<Pair>(query, &[&2, &2])?,
+        Some(Pair { n: 2, square: 4 })
+    );
+
+    assert!(matches!(
+        conn.query_scalar::<i64>("select * from missing_table;", &[]),
+        Err(PgError::Query { .. })
+    ));
+
+    Ok(())
+}
+
+/// ## Test: `execute_affected_rows`
+///
+/// Runs DML through `PgConn::execute`, which returns the affected-row count directly.
+///
+/// ### Assertions
+///
+/// - Inserting three rows returns `3`, and an `UPDATE` matching two of them returns `2`.
+/// - A statement that fails returns `PgError::Query`.
+#[test]
+fn execute_affected_rows() -> Result<(), PgError> {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    assert_eq!(conn.execute("create temp table t (n int);", &[])?, 0);
+    assert_eq!(
+        conn.execute("insert into t values ($1), ($2), ($3);", &[&1, &2, &3])?,
+        3
+    );
+    assert_eq!(
+        conn.execute("update t set n = n * 10 where n > $1;", &[&1])?,
+        2
+    );
+    assert!(matches!(
+        conn.execute("update missing_table set n = 0;", &[]),
+        Err(PgError::Query { .. })
+    ));
+
+    Ok(())
+}
+
+/// ## Test: `exec_many_results`
+///
+/// Runs multi-statement scripts through `PgConn::exec_many`.
+///
+/// ### Assertions
+///
+/// - `SELECT 1; SELECT 2;` returns two results, in order.
+/// - In a script whose second statement fails, the results stop at the failing one, whose
+///   `check()` reports the error, while the first statement's result is kept.
+/// - In a script starting a COPY, the results stop at the COPY one, whose `check()`
+///   reports `CopyInProgress`; once the copy is ended, the connection is usable again.
+#[test]
+fn exec_many_results() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let results = conn.exec_many("SELECT 1; SELECT 2;")?;
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].get_value_raw(0, 0), "1");
+    assert_eq!(results[1].get_value_raw(0, 0), "2");
+
+    let results =
+        conn.exec_many("create temp table t (n int); select * from missing_table; select 3;")?;
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].status(), ExecStatusType_PGRES_COMMAND_OK);
+    assert!(matches!(
+        results.into_iter().nth(1).unwrap().check(),
+        Err(PgError::Query { .. })
+    ));
+
+    let results = conn.exec_many("create temp table cp (n int); COPY cp FROM STDIN;")?;
+    assert_eq!(results.len(), 2);
+    assert!(matches!(
+        results.into_iter().nth(1).unwrap().check(),
+        Err(PgError::CopyInProgress(s)) if s == ExecStatusType_PGRES_COPY_IN
+    ));
+    conn.put_copy_end(None)?;
+    while conn.get_result().is_some() {}
+    assert_eq!(conn.exec("SELECT 3;")?.check()?.get_value_raw(0, 0), "3");
+
+    Ok(())
+}
+
+/// ## Test: `builder_connect`
+///
+/// Connects through `PgConnBuilder`, which passes keywords and values to
+/// `PQconnectdbParams` separately, leaving unset parameters to the environment variables.
+///
+/// ### Assertions
+///
+/// - An application name with spaces and a quote needs no escaping and is seen by the server.
+/// - A connection to a non-existent database fails with `PgError::Connection`.
+#[test]
+fn builder_connect() -> Result<(), PgError> {
+    let conn = PgConnBuilder::new()
+        .application_name("my 'test' app")
+        .connect_timeout(5)
+        .connect()?;
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+    assert_eq!(
+        conn.query_scalar::<String>("show application_name;", &[])?,
+        "my 'test' app"
+    );
+
+    assert!(matches!(
+        PgConnBuilder::new().dbname("no such database").connect(),
+        Err(PgError::Connection(_))
+    ));
+
+    Ok(())
+}
+
+/// ## Test: `set_application_name`
+///
+/// Changes the application name of an open connection with `PgConn::set_application_name`.
+///
+/// ### Assertions
+///
+/// - The name given to the builder is reported by `parameter_status`.
+/// - After `set_application_name`, both `parameter_status` and this backend's row in
+///   `pg_stat_activity` show the new name, quotes included.
+/// - An unknown parameter has no status.
+#[test]
+fn set_application_name() -> Result<(), PgError> {
+    let mut conn = PgConnBuilder::new().application_name("before").connect()?;
+
+    assert_eq!(
+        conn.parameter_status("application_name")?.as_deref(),
+        Some("before")
+    );
+
+    conn.set_application_name("after 'renamed'")?;
+
+    assert_eq!(
+        conn.parameter_status("application_name")?.as_deref(),
+        Some("after 'renamed'")
+    );
+    assert_eq!(
+        conn.query_scalar::<String>(
+            "select application_name from pg_stat_activity where pid = pg_backend_pid();",
+            &[],
+        )?,
+        "after 'renamed'"
+    );
+    assert_eq!(conn.parameter_status("no_such_parameter")?, None);
+
+    Ok(())
+}
+
+/// ## Test: `reconnect_restores_listen`
+///
+/// Verifies that `PgConn::reconnect` subscribes again to the channels the connection was
+/// listening on, after its backend has been terminated.
+///
+/// ### Assertions
+///
+/// - `listening` tracks `listen`/`listen_many`/`unlisten`.
+/// - After `pg_terminate_backend` and `reconnect`, the connection has a new backend PID and
+///   receives a notification sent on a channel it listened on before, but not one it left.
+#[test]
+fn reconnect_restores_listen() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.listen("Reconnect")?.check()?;
+    conn.listen_many(&["other", "left"])?;
+    conn.unlisten("left")?;
+
+    let mut channels: Vec<&str> = conn.listening().collect();
+    channels.sort();
+    assert_eq!(channels, vec!["Reconnect", "other"]);
+
+    let pid: i32 = conn.query_scalar("select pg_backend_pid();", &[])?;
+
+    let mut other =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+    assert!(other.query_scalar::<bool>("select pg_terminate_backend($1);", &[&pid])?);
+
+    conn.reconnect()?;
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+    assert_ne!(
+        conn.query_scalar::<i32>("select pg_backend_pid();", &[])?,
+        pid
+    );
+
+    other.notify("left", Some("ignored"))?.check()?;
+    other.notify("Reconnect", Some("restored"))?.check()?;
+
+    let payloads = conn.listen_loop(Some(1.0), |_i, notify| {
+        ControlFlow::Continue(Some((notify.relname(), notify.extra())))
+    })?;
+    assert_eq!(
+        payloads,
+        vec![("Reconnect".to_string(), "restored".to_string())]
+    );
+
+    Ok(())
+}
+
+/// ## Test: `connection_password_flags`
+///
+/// Checks `connection_needs_password` and `connection_used_password`, assuming the server
+/// requires password authentication as the test setup does.
+///
+/// ### Assertions
+///
+/// - With the password from the environment, the connection succeeds, used the password and
+///   needs no other.
+/// - With an explicitly empty password in the conninfo string, which overrides `PGPASSWORD`,
+///   the connection is bad and needs a password.
+#[test]
+fn connection_password_flags() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+    assert!(conn.connection_used_password());
+    assert!(!conn.connection_needs_password());
+
+    let conn =
+        PgConn::connect_db("password=''").expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_BAD);
+    assert!(conn.connection_needs_password());
+}
+
+/// ## Test: `pool_concurrent_checkout`
+///
+/// Shares a `PgPool` of at most two connections among eight threads.
+///
+/// ### Assertions
+///
+/// - Every thread checks out a connection and runs `SELECT 1`.
+/// - The pool never opens more than its maximum size, and connections are reused: all
+///   queries ran on at most two distinct backends.
+/// - A connection left broken by its user is reconnected on the next checkout.
+/// - A connection returned in an aborted transaction is rolled back, so the next one to
+///   check it out can run queries.
+/// - A connection returned with a query in progress is closed rather than reused.
+#[cfg(feature = "pool")]
+#[test]
+fn pool_concurrent_checkout() -> Result<(), PgError> {
+    use libpq::PgPool;
+
+    let pool = PgPool::new(PgConnBuilder::new(), 2);
+    let pids = Mutex::new(Vec::new());
+
+    thread::scope(|s| {
+        for _ in 0..8 {
+            s.spawn(|| {
+                let conn = pool.get().expect("Failed to check out a connection.");
+                assert_eq!(conn.query_scalar::<i32>("select 1;", &[]).unwrap(), 1);
+                let pid: i32 = conn.query_scalar("select pg_backend_pid();", &[]).unwrap();
+                pids.lock().unwrap().push(pid);
+                thread::sleep(Duration::from_millis(20));
+                assert!(pool.size() <= pool.max_size());
+            });
+        }
+    });
+
+    let mut pids = pids.into_inner().unwrap();
+    pids.sort();
+    pids.dedup();
+    assert!(pids.len() <= 2);
+    assert_eq!(pool.size(), pids.len());
+
+    {
+        let conn = pool.get()?;
+        let pid: i32 = conn.query_scalar("select pg_backend_pid();", &[])?;
+        let _ = conn.query_scalar::<bool>("select pg_terminate_backend($1);", &[&pid]);
+    }
+
+    // The broken connection is the most recently returned one, so it is checked out next.
+    let conn = pool.get()?;
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+    assert_eq!(conn.query_scalar::<i32>("select 1;", &[])?, 1);
+    drop(conn);
+
+    let pid = {
+        let conn = pool.get()?;
+        let pid: i32 = conn.query_scalar("select pg_backend_pid();", &[])?;
+        conn.exec("BEGIN;")?.check()?;
+        assert!(conn.exec("SELECT 1/0;")?.check().is_err());
+        pid
+    };
+    let conn = pool.get()?;
+    assert_eq!(
+        conn.query_scalar::<i32>("select pg_backend_pid();", &[])?,
+        pid
+    );
+    assert_eq!(conn.query_scalar::<i32>("select 1;", &[])?, 1);
+    drop(conn);
+
+    let size = pool.size();
+    {
+        let mut conn = pool.get()?;
+        conn.send_query("select pg_sleep(0.1);")?;
+    }
+    assert_eq!(pool.size(), size - 1);
+
+    Ok(())
+}
+
+/// ## Test: `async_exec`
+///
+/// Awaits queries on a tokio runtime through `AsyncPgConn`, whose socket readiness drives
+/// `send_query`/`consume_input`/`get_result`.
+///
+/// ### Assertions
+///
+/// - `SELECT 1` resolves to a `TUPLES_OK` result with value `1`.
+/// - A slow query on one connection doesn't block another one on the same runtime thread.
+/// - Errors come back as a `FATAL_ERROR` result, like `PgConn::exec`.
+/// - A COPY statement resolves to its `COPY_OUT` result instead of waiting forever.
+/// - After a query is cancelled by a timeout, the next one runs and gets its own result.
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn async_exec() -> Result<(), PgError> {
+    use libpq::AsyncPgConn;
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let mut conn = AsyncPgConn::new(conn)?;
+    assert!(conn.conn().is_nonblocking());
+
+    let res = conn.exec("SELECT 1;").await?.check()?;
+    assert_eq!(res.get_value_raw(0, 0), "1");
+
+    let mut slow = AsyncPgConn::new(PgConnBuilder::new().connect()?)?;
+    let started = Instant::now();
+    let (slow_res, fast_res) = tokio::join!(
+        slow.exec("select pg_sleep(0.5), 'slow';"),
+        conn.exec("select 'fast';")
+    );
+    assert_eq!(fast_res?.get_value_raw(0, 0), "fast");
+    assert_eq!(slow_res?.get_value_raw(0, 1), "slow");
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    let res = conn.exec("select * from missing_table;").await?;
+    assert_eq!(res.status(), ExecStatusType_PGRES_FATAL_ERROR);
+
+    let res = tokio::time::timeout(
+        Duration::from_secs(3),
+        conn.exec("COPY (SELECT 1) TO STDOUT;"),
+    )
+    .await
+    .expect("COPY did not resolve.")?;
+    assert_eq!(res.status(), ExecStatusType_PGRES_COPY_OUT);
+    // That connection is left in the middle of the copy.
+    let mut conn = AsyncPgConn::new(PgConnBuilder::new().connect()?)?;
+
+    let cancelled = tokio::time::timeout(
+        Duration::from_millis(50),
+        conn.exec("select pg_sleep(0.3), 'cancelled';"),
+    )
+    .await;
+    assert!(cancelled.is_err());
+    let res = conn.exec("select 'next';").await?.check()?;
+    assert_eq!(res.get_value_raw(0, 0), "next");
+
+    Ok(())
+}
+
+/// ## Test: `async_notifications`
+///
+/// Consumes `LISTEN`/`NOTIFY` notifications as a stream from `AsyncPgConn::notifications`.
+///
+/// ### Assertions
+///
+/// - Payloads sent by another task after subscribing are yielded in order.
+/// - Once the listening backend is terminated, the stream yields a `PgError::Connection`
+///   and then ends.
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn async_notifications() -> Result<(), PgError> {
+    use futures_util::StreamExt;
+    use libpq::AsyncPgConn;
+
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let mut conn = AsyncPgConn::new(conn)?;
+    conn.exec("LISTEN async_channel;").await?.check()?;
+    let pid = conn
+        .exec("select pg_backend_pid();")
+        .await?
+        .get::<i32>(0, 0)?;
+
+    let sender = tokio::spawn(async move {
+        let mut other = AsyncPgConn::new(PgConnBuilder::new().connect()?)?;
+        for payload in ["one", "two"] {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            other
+                .exec(&format!("NOTIFY async_channel, '{}';", payload))
+                .await?
+                .check()?;
+        }
+        Ok::<_, PgError>(other)
+    });
+
+    let mut notifications = conn.notifications();
+    let mut payloads = Vec::new();
+    while payloads.len() < 2 {
+        let notify = tokio::time::timeout(Duration::from_secs(5), notifications.next())
+            .await
+            .expect("Timed out waiting for a notification.")
+            .expect("The stream ended early.")?;
+        assert_eq!(notify.relname(), "async_channel");
+        payloads.push(notify.extra());
+    }
+    assert_eq!(payloads, vec!["one", "two"]);
+
+    let mut other = sender.await.expect("Sender task panicked.")?;
+    other
+        .exec(&format!("select pg_terminate_backend({});", pid))
+        .await?
+        .check()?;
+
+    let end = tokio::time::timeout(Duration::from_secs(5), async {
+        let error = notifications.next().await;
+        (error, notifications.next().await.is_none())
+    })
+    .await
+    .expect("Timed out waiting for the stream to end.");
+    assert!(matches!(end, (Some(Err(PgError::Connection(_))), true)));
+
+    Ok(())
+}
+
+/// ## Test: `transaction_savepoint`
+///
+/// Uses the `Transaction` and `Savepoint` guards to undo part of a transaction.
+///
+/// ### Assertions
+///
+/// - A row inserted after a savepoint that is rolled back on drop disappears, while the row
+///   inserted before it is kept once the transaction commits.
+/// - A released savepoint keeps its changes, even with a name that needs quoting.
+/// - Dropping a transaction without committing rolls it back.
+#[test]
+fn transaction_savepoint() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.execute("create temp table t (n int);", &[])?;
+
+    let mut tx = conn.transaction()?;
+    tx.execute("insert into t values (1);", &[])?;
+    {
+        let sp = tx.savepoint("first")?;
+        sp.execute("insert into t values (2);", &[])?;
+    }
+    let sp = tx.savepoint("Kept \"one\"")?;
+    sp.execute("insert into t values (3);", &[])?;
+    sp.commit()?;
+    tx.commit()?;
+
+    {
+        let tx = conn.transaction()?;
+        tx.execute("insert into t values (4);", &[])?;
+    }
+
+    let res = conn.exec("select n from t order by n;")?.check()?;
+    let values = res
+        .rows()
+        .map(|row| row.get::<i32, _>(0))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(values, vec![1, 3]);
+
+    Ok(())
+}
+
+/// ## Test: `copy_in_rows`
+///
+/// Bulk-loads rows with `PgConn::copy_in`, which serializes them in the COPY text format.
+///
+/// ### Assertions
+///
+/// - All 1000 rows are loaded and counted.
+/// - A NULL, a tab, a newline and a backslash survive the round trip unchanged.
+/// - Loading into a missing table fails with `PgError::Query`.
+/// - On a non-blocking connection, every row is still loaded, over many chunks.
+#[test]
+fn copy_in_rows() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.execute("create temp table \"Items\" (id int, label text);", &[])?;
+
+    let rows = (1..=1000).map(|i| {
+        let label = match i {
+            1 => None,
+            2 => Some("tab\there".to_string()),
+            3 => Some("new\nline and back\\slash".to_string()),
+            i => Some(format!("item {}", i)),
+        };
+        vec![Some(i.to_string()), label]
+    });
+
+    assert_eq!(conn.copy_in("Items", &["id", "label"], rows)?, 1000);
+
+    assert_eq!(
+        conn.query_scalar::<i64>("select count(*) from \"Items\";", &[])?,
+        1000
+    );
+    let res = conn
+        .exec("select label from \"Items\" where id <= 3 order by id;")?
+        .check()?;
+    assert!(res.is_null(0, 0));
+    assert_eq!(res.get_value_raw(1, 0), "tab\there");
+    assert_eq!(res.get_value_raw(2, 0), "new\nline and back\\slash");
+
+    assert!(matches!(
+        conn.copy_in("missing_table", &[], vec![vec![None]]),
+        Err(PgError::Query { .. })
+    ));
+
+    conn.set_nonblocking(true)?;
+    let rows = (1..=200_000).map(|i| vec![Some(i.to_string()), Some(format!("row {}", i))]);
+    assert_eq!(conn.copy_in("Items", &["id", "label"], rows)?, 200_000);
+    conn.set_nonblocking(false)?;
+    assert_eq!(
+        conn.query_scalar::<i64>("select count(*) from \"Items\";", &[])?,
+        201_000
+    );
+
+    Ok(())
+}
+
+/// ## Test: `copy_query_to_csv`
+///
+/// Streams the output of a query as CSV into a `Vec<u8>` with `PgConn::copy_query_to_csv`.
+///
+/// ### Assertions
+///
+/// - Exporting `SELECT generate_series(1,1000)` reports 1000 rows and writes a header plus
+///   1000 lines, from `1` to `1000`.
+/// - Values needing quotes are quoted as CSV, and a failing query is a `PgError::Query`.
+#[test]
+fn copy_query_to_csv() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let mut out = Vec::new();
+    let rows = conn.copy_query_to_csv("SELECT generate_series(1,1000) AS n;", &mut out)?;
+    assert_eq!(rows, 1000);
+
+    let text = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 1001);
+    assert_eq!(lines[0], "n");
+    assert_eq!(lines[1], "1");
+    assert_eq!(lines[1000], "1000");
+
+    let mut out = Vec::new();
+    conn.copy_query_to_csv("select 'a,b' as x, null as y", &mut out)?;
+    assert_eq!(String::from_utf8(out).unwrap(), "x,y\n\"a,b\",\n");
+
+    assert!(matches!(
+        conn.copy_query_to_csv("select * from missing_table", &mut Vec::new()),
+        Err(PgError::Query { .. })
+    ));
+
+    Ok(())
+}
+
+/// ## Test: `statement_timeout`
+///
+/// Sets the session's statement timeout from a `Duration` with
+/// `PgConn::set_statement_timeout`.
+///
+/// ### Assertions
+///
+/// - With `100ms`, `SHOW statement_timeout` reports `100ms` and `SELECT pg_sleep(1)` fails
+///   with SQLSTATE `57014` (query canceled).
+/// - A sub-millisecond timeout is rounded up to `1ms` rather than disabling the timeout.
+/// - `None` restores the default, so the same query runs to completion.
+#[test]
+fn statement_timeout() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.set_statement_timeout(Some(Duration::from_millis(100)))?;
+    assert_eq!(
+        conn.query_scalar::<String>("show statement_timeout;", &[])?,
+        "100ms"
+    );
+
+    match conn.exec("SELECT pg_sleep(1);")?.check() {
+        Err(PgError::Query { sqlstate, .. }) => assert_eq!(sqlstate.as_deref(), Some("57014")),
+        other => panic!("Expected a canceled query, got {:?}", other.map(|_| ())),
+    }
+
+    conn.set_statement_timeout(Some(Duration::from_micros(10)))?;
+    assert_eq!(
+        conn.query_scalar::<String>("show statement_timeout;", &[])?,
+        "1ms"
+    );
+
+    conn.set_statement_timeout(None)?;
+    conn.exec("SELECT pg_sleep(0.2);")?.check()?;
+
+    Ok(())
+}
+
+/// ## Test: `search_path_and_role`
+///
+/// Switches schema and role with `set_search_path`, `set_role` and `reset_role`, inside a
+/// transaction that is rolled back to leave the database untouched.
+///
+/// ### Assertions
+///
+/// - After `set_search_path(&["Tenant One"])`, an unqualified table is created in and read
+///   from that schema.
+/// - A schema name attempting injection is taken as a plain (missing) name.
+/// - `set_role` changes `current_user`, and `reset_role` restores it.
+#[test]
+fn search_path_and_role() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let user: String = conn.query_scalar("select current_user;", &[])?;
+
+    let mut tx = conn.transaction()?;
+    tx.execute("create schema \"Tenant One\";", &[])?;
+    tx.set_search_path(&["Tenant One"])?;
+    tx.execute("create table items (n int);", &[])?;
+    tx.execute("insert into items values (7);", &[])?;
+
+    assert_eq!(tx.query_scalar::<i32>("select n from items;", &[])?, 7);
+    assert_eq!(
+        tx.query_scalar::<String>(
+            "select table_schema::text from information_schema.tables where table_name = 'items';",
+            &[],
+        )?,
+        "Tenant One"
+    );
+
+    tx.set_search_path(&["public; drop table items"])?;
+    assert_eq!(
+        tx.query_scalar::<String>("show search_path;", &[])?,
+        "\"public; drop table items\""
+    );
+
+    tx.execute("create role \"Tenant Role\";", &[])?;
+    tx.set_role("Tenant Role")?;
+    assert_eq!(
+        tx.query_scalar::<String>("select current_user::text;", &[])?,
+        "Tenant Role"
+    );
+    tx.reset_role()?;
+    assert_eq!(
+        tx.query_scalar::<String>("select current_user::text;", &[])?,
+        user
+    );
+
+    tx.rollback()
+}
+
+/// ## Test: `send_params_and_prepared`
+///
+/// Submits parameterized and prepared statements asynchronously with `send_query_params`,
+/// `send_prepare` and `send_query_prepared`, then waits on the socket and drains the results.
+///
+/// ### Assertions
+///
+/// - Each call returns immediately, and after polling to readiness `get_result` yields one
+///   successful result followed by `None`.
+/// - Parameters are bound correctly, including a NULL and a type given to `send_prepare`.
+#[test]
+fn send_params_and_prepared() -> Result<(), PgError> {
+    fn drain(conn: &mut PgConn) -> Result<Vec<PgResult>, PgError> {
+        while conn.is_busy() {
+            assert!(conn.socket().poll(true, false, Some(5.0)).is_ok());
+            conn.consume_input().map_err(PgError::Connection)?;
+        }
+        Ok(std::iter::from_fn(|| conn.get_result()).collect())
+    }
+
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let missing: Option<&str> = None;
+    conn.send_query_params(
+        "select $1::int + $2, $3::text is null;",
+        &[&40, &2, &missing],
+    )?;
+    let results = drain(&mut conn)?;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].status(), ExecStatusType_PGRES_TUPLES_OK);
+    assert_eq!(results[0].get::<i32>(0, 0)?, 42);
+    assert!(results[0].get::<bool>(0, 1)?);
+
+    conn.send_prepare("greet", "select 'hello ' || $1;", &[TEXTOID])?;
+    let results = drain(&mut conn)?;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].status(), ExecStatusType_PGRES_COMMAND_OK);
+
+    conn.send_query_prepared("greet", &[&"world"])?;
+    let results = drain(&mut conn)?;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].get::<String>(0, 0)?, "hello world");
+
+    Ok(())
+}
+
+/// ## Test: `chunked_rows_mode`
+///
+/// Fetches 1000 rows in chunks of 100 with `set_chunked_rows_mode` and `chunked_results`.
+///
+/// ### Assertions
+///
+/// - The mode can be set right after `send_query`, but not without a query in progress.
+/// - Ten `TUPLES_CHUNK` results of 100 rows each arrive, in order, followed by a final empty
+///   `TUPLES_OK` result.
+#[test]
+fn chunked_rows_mode() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.send_query("select generate_series(1, 1000);")?;
+    assert!(conn.set_chunked_rows_mode(100));
+
+    let chunks: Vec<PgResult> = conn.chunked_results().collect();
+    assert_eq!(chunks.len(), 11);
+
+    for (i, chunk) in chunks[..10].iter().enumerate() {
+        assert_eq!(chunk.status_enum(), ExecStatus::TuplesChunk);
+        assert_eq!(chunk.ntuples(), 100);
+        assert_eq!(chunk.get::<i32>(0, 0)?, i as i32 * 100 + 1);
+    }
+    assert_eq!(chunks[10].status(), ExecStatusType_PGRES_TUPLES_OK);
+    assert_eq!(chunks[10].ntuples(), 0);
+
+    assert!(!conn.set_chunked_rows_mode(100));
+
+    Ok(())
+}
+
+/// ## Test: `debug_formatting`
+///
+/// Formats a connection, a result and a notification with `{:?}`.
+///
+/// ### Assertions
+///
+/// - `PgConn` shows its database, user, port and status, as `db`/`user`/`port` report them.
+/// - `PgResult` shows its status and dimensions, and `PgNotify` its channel, PID and payload.
+/// - A connection that failed to connect can still be formatted.
+#[test]
+fn debug_formatting() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let db = conn.db().expect("Missing database name.");
+    let user = conn.user().expect("Missing user name.");
+    let port = conn.port().expect("Missing port.");
+    let debug = format!("{:?}", conn);
+    assert!(debug.starts_with("PgConn {"));
+    assert!(debug.contains(&format!("db: Some({:?})", db)));
+    assert!(debug.contains(&format!("user: Some({:?})", user)));
+    assert!(debug.contains(&format!("port: Some({:?})", port)));
+    assert!(debug.contains("status: Ok"));
+
+    let res = conn.exec("select 1, 2 union all select 3, 4;")?;
+    assert_eq!(
+        format!("{:?}", res),
+        "PgResult { status: TuplesOk, ntuples: 2, nfields: 2 }"
+    );
+
+    conn.listen("debug_channel")?.check()?;
+    conn.notify("debug_channel", Some("payload"))?.check()?;
+    let pid: i32 = conn.query_scalar("select pg_backend_pid();", &[])?;
+    let notify = conn.notifies().expect("Missing notification.");
+    assert_eq!(
+        format!("{:?}", notify),
+        format!(
+            "PgNotify {{ relname: \"debug_channel\", be_pid: {}, extra: \"payload\" }}",
+            pid
+        )
+    );
+
+    let bad =
+        PgConn::connect_db("port=1").expect("Failed to create PGconn from connection string.");
+    assert!(format!("{:?}", bad).contains("status: Bad"));
+
+    Ok(())
+}
+
+/// ## Test: `strict_utf8_accessors`
+///
+/// Compares the lossy and strict string accessors on data that is not valid UTF-8, obtained
+/// by switching the client encoding to `LATIN1`.
+///
+/// ### Assertions
+///
+/// - `get_value_raw` replaces the invalid byte with U+FFFD, while `get_value_raw_strict`
+///   returns a `Utf8Error`.
+/// - The same holds for a notification payload with `extra`/`extra_strict`.
+/// - Valid values and messages decode with the strict accessors too.
+#[test]
+fn strict_utf8_accessors() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.listen("latin1")?.check()?;
+    conn.exec("set client_encoding = 'LATIN1';")?.check()?;
+
+    let res = conn.exec("select 'caf' || chr(233), 'plain';")?.check()?;
+    assert_eq!(res.get_value_raw(0, 0), "caf\u{FFFD}");
+    assert!(res.get_value_raw_strict(0, 0).is_err());
+    assert_eq!(res.get_value_raw_strict(0, 1).as_deref(), Ok("plain"));
+    assert_eq!(res.error_message_strict().as_deref(), Ok(""));
+
+    conn.exec("select pg_notify('latin1', 'caf' || chr(233));")?
+        .check()?;
+    let notify = conn.notifies().expect("Missing notification.");
+    assert_eq!(notify.relname_strict().as_deref(), Ok("latin1"));
+    assert_eq!(notify.extra(), "caf\u{FFFD}");
+    assert!(notify.extra_strict().is_err());
+
+    let res = conn.exec("select * from missing_table;")?;
+    assert!(
+        res.error_message_strict()
+            .unwrap()
+            .contains("missing_table")
+    );
+    assert!(
+        conn.error_message_strict()
+            .unwrap()
+            .contains("missing_table")
+    );
+
+    Ok(())
+}
+
+/// ## Test: `cmd_status_shared_borrow`
+///
+/// Calls `cmd_status`, which only needs `&self`, alongside other accessors on the same
+/// immutable binding.
+///
+/// ### Assertions
+///
+/// - The tags of an `INSERT` and a `SELECT` are read while holding a shared borrow of the
+///   result, together with `status` and `cmd_tuples`.
+#[test]
+fn cmd_status_shared_borrow() -> Result<(), PgError> {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.execute("create temp table t (n int);", &[])?;
+
+    let res = conn.exec("insert into t values (1), (2);")?;
+    let shared = &res;
+    assert_eq!(shared.cmd_status(), "INSERT 0 2");
+    assert_eq!(res.status(), ExecStatusType_PGRES_COMMAND_OK);
+    assert_eq!(shared.cmd_tuples(), 2);
+
+    let res = conn.exec("select * from t;")?;
+    let (tag, status) = (res.cmd_status(), res.status());
+    assert_eq!(tag, "SELECT 2");
+    assert_eq!(status, ExecStatusType_PGRES_TUPLES_OK);
+
+    Ok(())
+}
+
+/// ## Test: `wait_for_notification`
+///
+/// Waits for single notifications with `PgConn::wait_for_notification`.
+///
+/// ### Assertions
+///
+/// - With nothing sent, a `200ms` wait returns `None` after roughly that long.
+/// - A notification sent from another thread after `100ms` is returned well within a `5s`
+///   timeout, with its payload.
+#[test]
+fn wait_for_notification() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.listen("waiting")?.check()?;
+
+    let started = Instant::now();
+    assert!(
+        conn.wait_for_notification(Some(Duration::from_millis(200)))?
+            .is_none()
+    );
+    assert!(started.elapsed() >= Duration::from_millis(150));
+
+    let sender = thread::spawn(|| {
+        thread::sleep(Duration::from_millis(100));
+        let mut conn =
+            PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+        conn.notify("waiting", Some("hello"))
+            .unwrap()
+            .check()
+            .unwrap();
+    });
+
+    let started = Instant::now();
+    let notify = conn
+        .wait_for_notification(Some(Duration::from_secs(5)))?
+        .expect("No notification within the timeout.");
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(notify.relname(), "waiting");
+    assert_eq!(notify.extra(), "hello");
+
+    sender.join().expect("Sender thread panicked.");
+
+    Ok(())
+}
+
+/// ## Test: `drain_notifications`
+///
+/// Collects queued notifications with `PgConn::drain_notifications`.
+///
+/// ### Assertions
+///
+/// - Three notifications sent in one transaction by another connection are all yielded, in
+///   order, after a single `consume_input`.
+/// - Draining again yields nothing, since no input is read by the iterator itself.
+#[test]
+fn drain_notifications() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.listen("drained")?.check()?;
+
+    let other =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+    other
+        .exec("begin; notify drained, 'a'; notify drained, 'b'; notify drained, 'c'; commit;")?
+        .check()?;
+
+    assert!(conn.socket().poll(true, false, Some(5.0)).is_ok());
+    thread::sleep(Duration::from_millis(50));
+    conn.consume_input().map_err(PgError::Connection)?;
+
+    let payloads: Vec<String> = conn.drain_notifications().map(|n| n.extra()).collect();
+    assert_eq!(payloads, vec!["a", "b", "c"]);
+    assert_eq!(conn.drain_notifications().count(), 0);
+
+    Ok(())
+}
+
+/// ## Test: `threadsafe_build`
+///
+/// Verifies that the linked libpq reports a thread-safe build, which the `Send`/`Sync`
+/// implementations of `PgConn` assume.
+///
+/// ### Assertions
+///
+/// - `is_threadsafe` returns `true`.
+#[test]
+fn threadsafe_build() {
+    assert!(is_threadsafe());
+}
+
+/// ## Test: `multibyte_lengths`
+///
+/// Verifies that `mblen` and `dsplen` measure characters under the client encoding.
+///
+/// ### Assertions
+///
+/// - Under `UTF8`, the first character of a string is one byte long for `a`, two for `é` and
+///   three for `日`.
+/// - `dsplen` counts `日` as two columns (a wide character), `é` as one, and sums over strings.
+/// - An empty string has a length of 0.
+#[test]
+fn multibyte_lengths() -> Result<(), PgError> {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.exec("SET client_encoding TO 'UTF8';")?.check()?;
+
+    assert_eq!(conn.mblen("a"), 1);
+    assert_eq!(conn.mblen("é"), 2);
+    assert_eq!(conn.mblen("日本"), 3);
+    assert_eq!(conn.mblen(""), 0);
+
+    assert_eq!(conn.dsplen("é"), 1);
+    assert_eq!(conn.dsplen("日"), 2);
+    assert_eq!(conn.dsplen("日本 é"), 6);
+    assert_eq!(conn.dsplen(""), 0);
+
+    Ok(())
+}
+
+/// ## Test: `copy_in_reader`
+///
+/// Streams pre-formatted COPY text data from a reader with `PgConn::copy_in_reader`.
+///
+/// ### Assertions
+///
+/// - The rows of an in-memory TSV, one of them with a NULL, are all loaded and counted.
+/// - A reader that fails aborts the copy with `PgError::Io`, loading nothing, and the
+///   connection stays usable.
+/// - Both work the same on a non-blocking connection.
+#[test]
+fn copy_in_reader() -> Result<(), PgError> {
+    struct Failing;
+
+    impl Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk on fire"))
+        }
+    }
+
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.execute("create temp table tsv (id int, label text);", &[])?;
+
+    let mut src = Cursor::new("1\tone\n2\t\\N\n3\tthree\n");
+    assert_eq!(conn.copy_in_reader("tsv (id, label)", &mut src)?, 3);
+    assert_eq!(
+        conn.query_scalar::<i64>("select count(*) from tsv where label is null;", &[])?,
+        1
+    );
+
+    assert!(matches!(
+        conn.copy_in_reader("tsv", &mut Failing),
+        Err(PgError::Io(_))
+    ));
+    assert_eq!(
+        conn.query_scalar::<i64>("select count(*) from tsv;", &[])?,
+        3
+    );
+
+    conn.set_nonblocking(true)?;
+    let mut src = Cursor::new(
+        "4	four
+"
+        .repeat(50_000),
+    );
+    assert_eq!(conn.copy_in_reader("tsv (id, label)", &mut src)?, 50_000);
+    assert!(matches!(
+        conn.copy_in_reader("tsv", &mut Failing),
+        Err(PgError::Io(_))
+    ));
+    conn.set_nonblocking(false)?;
+    assert_eq!(
+        conn.query_scalar::<i64>("select count(*) from tsv;", &[])?,
+        50_003
+    );
+
+    Ok(())
+}
+
+/// ## Test: `binary_copy_round_trip`
+///
+/// Loads rows of mixed types with a binary `COPY FROM STDIN` through `BinaryCopyWriter`, then
+/// reads them back with a binary `COPY TO STDOUT` through `BinaryCopyReader`.
+///
+/// ### Assertions
+///
+/// - Every row is loaded and counted, and the server decodes the values as written.
+/// - Every value, floats included, is read back exactly, and NULLs decode as `None`.
+/// - A value whose encoding doesn't match its column's type makes `finish` fail with
+///   `PgError::Query`, and dropping an unfinished writer aborts the copy; in both cases the
+///   connection stays usable and nothing is loaded.
+/// - A row with more fields than the format allows is rejected as invalid input, leaving
+///   the copy unaffected.
+/// - Dropping a writer on a non-blocking connection, or a reader before its last row,
+///   returns without hanging and leaves the connection usable.
+#[test]
+fn binary_copy_round_trip() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.execute(
+        "create temp table mixed (a int2, b int4, c int8, d float4, e float8, f bool, g text, h bytea);",
+        &[],
+    )?;
+
+    let mut writer = conn.copy_in_binary("mixed")?;
+    writer.write_row(&[
+        &1i16,
+        &-2i32,
+        &3_000_000_000i64,
+        &0.1f32,
+        &0.1f64,
+        &true,
+        &"tab\tand ünicode",
+        &vec![0u8, 255, 10],
+    ])?;
+    writer.write_row(&[
+        &None::<i16>,
+        &None::<i32>,
+        &None::<i64>,
+        &None::<f32>,
+        &f64::MIN_POSITIVE,
+        &false,
+        &None::<&str>,
+        &None::<Vec<u8>>,
+    ])?;
+    assert_eq!(writer.finish()?, 2);
+
+    let res = conn
+        .exec("select c, e = 0.1, g, h from mixed where a = 1;")?
+        .check()?;
+    assert_eq!(res.get_value_raw(0, 0), "3000000000");
+    assert_eq!(res.get_value_raw(0, 1), "t");
+    assert_eq!(res.get_value_raw(0, 2), "tab\tand ünicode");
+    assert_eq!(res.get_value_raw(0, 3), "\\x00ff0a");
+
+    let rows = conn
+        .copy_out_binary("select * from mixed order by a nulls last;")?
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].len(), 8);
+    assert_eq!(rows[0].get::<i16>(0)?, 1);
+    assert_eq!(rows[0].get::<i32>(1)?, -2);
+    assert_eq!(rows[0].get::<i64>(2)?, 3_000_000_000);
+    assert_eq!(rows[0].get::<f32>(3)?, 0.1);
+    assert_eq!(rows[0].get::<f64>(4)?, 0.1);
+    assert!(rows[0].get::<bool>(5)?);
+    assert_eq!(rows[0].get::<String>(6)?, "tab\tand ünicode");
+    assert_eq!(rows[0].get::<Vec<u8>>(7)?, vec![0, 255, 10]);
+    assert!(rows[1].is_null(0));
+    assert_eq!(rows[1].get::<Option<i32>>(1)?, None);
+    assert!(matches!(
+        rows[1].get::<i64>(2),
+        Err(PgError::UnexpectedNull { row: 1, col: 2 })
+    ));
+    assert_eq!(rows[1].get::<f64>(4)?, f64::MIN_POSITIVE);
+    assert!(!rows[1].get::<bool>(5)?);
+    assert!(matches!(
+        rows[1].get::<String>(8),
+        Err(PgError::ColumnNotFound(_))
+    ));
+
+    let mut writer = conn.copy_in_binary("mixed (b)")?;
+    writer.write_row(&[&1i64])?;
+    assert!(matches!(writer.finish(), Err(PgError::Query { .. })));
+
+    let mut writer = conn.copy_in_binary("mixed (b)")?;
+    writer.write_row(&[&1i32])?;
+    drop(writer);
+
+    assert_eq!(
+        conn.query_scalar::<i64>("select count(*) from mixed;", &[])?,
+        2
+    );
+
+    let mut writer = conn.copy_in_binary("mixed (b)")?;
+    let nulls = vec![&None::<i32> as &dyn ToBinary; 40_000];
+    assert!(matches!(
+        writer.write_row(&nulls),
+        Err(PgError::Io(e)) if e.kind() == std::io::ErrorKind::InvalidInput
+    ));
+    writer.write_row(&[&7i32])?;
+    assert_eq!(writer.finish()?, 1);
+
+    conn.set_nonblocking(true)?;
+    let mut writer = conn.copy_in_binary("mixed (b)")?;
+    writer.write_row(&[&8i32])?;
+    drop(writer);
+    conn.set_nonblocking(false)?;
+
+    let mut reader = conn.copy_out_binary("select generate_series(1, 10000);")?;
+    assert_eq!(reader.next_row()?.unwrap().get::<i32>(0)?, 1);
+    drop(reader);
+    assert_eq!(
+        conn.query_scalar::<i64>("select count(*) from mixed;", &[])?,
+        3
+    );
+
+    Ok(())
+}
+
+/// ## Test: `connection_is_ok_is_bad`
+///
+/// Checks the `is_ok`/`is_bad` status predicates on a good and a failed connection.
+///
+/// ### Assertions
+///
+/// - A connection from the environment is OK and not bad.
+/// - A connection to a port nothing listens on is bad and not OK.
+#[test]
+fn connection_is_ok_is_bad() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert!(conn.is_ok());
+    assert!(!conn.is_bad());
+
+    let conn = PgConn::connect_db("host=localhost port=1 connect_timeout=2")
+        .expect("Failed to create PGconn from connection string.");
+
+    assert!(conn.is_bad());
+    assert!(!conn.is_ok());
+}
+
+/// ## Test: `get_length_and_bytes`
+///
+/// Checks that values are read using their length rather than up to the first NUL byte,
+/// which a value with an embedded NUL (set here in a synthetic result) would stop at.
+///
+/// ### Assertions
+///
+/// - `get_length` reports the full length, past the NUL byte that `CStr` would stop at.
+/// - `get_bytes` and `get_value_raw` return the whole value.
+/// - A NULL has a length of 0 and no bytes; a query value's length matches its text.
+#[test]
+fn get_length_and_bytes() -> Result<(), PgError> {
+    let mut res = PgResult::make_empty(ExecStatusType_PGRES_TUPLES_OK);
+    assert!(res.set_attrs(&[("v", TEXTOID)])?);
+    assert!(res.set_value(0, 0, Some("ab\0cd")));
+    assert!(res.set_value(1, 0, None));
+
+    let truncated = unsafe { std::ffi::CStr::from_ptr(res.get_bytes(0, 0).as_ptr() as *const _) };
+    assert_eq!(truncated.to_bytes().len(), 2);
+    assert_eq!(res.get_length(0, 0), 5);
+    assert_eq!(res.get_bytes(0, 0), b"ab\0cd");
+    assert_eq!(res.get_value_raw(0, 0), "ab\0cd");
+
+    assert_eq!(res.get_length(1, 0), 0);
+    assert!(res.get_bytes(1, 0).is_empty());
+
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn.exec("select 'héllo';")?.check()?;
+    assert_eq!(res.get_length(0, 0), 6);
+    assert_eq!(res.get_bytes(0, 0), "héllo".as_bytes());
+
+    Ok(())
+}
+
+/// ## Test: `restore_previous_notice_handlers`
+///
+/// Verifies that `set_notice_processor` and `set_notice_receiver` return the handler they
+/// replace, which `restore_notice_processor`/`restore_notice_receiver` reinstate.
+///
+/// ### What it does
+///
+/// - Installs processor A, then processor B, checking that B receives a notice.
+/// - Restores A from the handle returned when installing B, raises another notice and
+///   asserts A receives it while B's closure has been dropped.
+/// - Does the same with two notice receivers.
+#[test]
+fn restore_previous_notice_handlers() {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let raise = |conn: &PgConn, message: &str| {
+        conn.exec(&format!("do $$ begin raise notice '{}'; end $$;", message))
+            .expect("Failed to execute query.");
+    };
+
+    let (tx_a, rx_a) = mpsc::channel();
+    let (tx_b, rx_b) = mpsc::channel();
+
+    conn.set_notice_processor(move |s| tx_a.send(s).unwrap());
+    let a = conn.set_notice_processor(move |s| tx_b.send(s).unwrap());
+
+    raise(&conn, "to b");
+    assert_eq!(rx_b.try_recv(), Ok("NOTICE:  to b\n".to_string()));
+    assert_eq!(rx_a.try_recv(), Err(mpsc::TryRecvError::Empty));
+
+    conn.restore_notice_processor(a);
+
+    raise(&conn, "to a");
+    assert_eq!(rx_a.try_recv(), Ok("NOTICE:  to a\n".to_string()));
+    assert_eq!(rx_b.try_recv(), Err(mpsc::TryRecvError::Disconnected));
+
+    let (tx_a, rx_a) = mpsc::channel();
+    let (tx_b, rx_b) = mpsc::channel();
+
+    conn.set_notice_receiver(move |r| tx_a.send(r.error_message()).unwrap());
+    let a = conn.set_notice_receiver(move |r| tx_b.send(r.error_message()).unwrap());
+
+    raise(&conn, "to b");
+    assert_eq!(rx_b.try_recv(), Ok("NOTICE:  to b\n".to_string()));
+    assert_eq!(rx_a.try_recv(), Err(mpsc::TryRecvError::Empty));
+
+    conn.restore_notice_receiver(a);
+
+    raise(&conn, "to a");
+    assert_eq!(rx_a.try_recv(), Ok("NOTICE:  to a\n".to_string()));
+    assert_eq!(rx_b.try_recv(), Err(mpsc::TryRecvError::Disconnected));
+}
+
+/// ## Test: `prepared_statement_handle`
+///
+/// Prepares a statement once with `PgConn::prepare_statement` and runs it several times
+/// through the returned `PreparedStatement`.
+///
+/// ### Assertions
+///
+/// - Three executions with different parameters return the expected rows, the parameter
+///   type given at preparation applying to each of them.
+/// - The connection stays usable through `&mut` while the handle lives, e.g. for a
+///   transaction.
+/// - The statement is listed in `pg_prepared_statements` under its generated name until
+///   it is closed, and deallocated then.
+/// - Preparing an invalid query fails with `PgError::Query`.
+#[test]
+fn prepared_statement_handle() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let stmt = conn.prepare_statement("select $1 * 2;", &[INT8OID])?;
+
+    for i in [1i64, 20, -300] {
+        let res = stmt.execute(&conn, &[&i])?.check()?;
+        assert_eq!(res.get::<i64>(0, 0)?, i * 2);
+    }
+
+    let tx = conn.transaction()?;
+    assert_eq!(stmt.execute(&tx, &[&4i64])?.get::<i64>(0, 0)?, 8);
+    tx.commit()?;
+
+    let name = stmt.name().to_string();
+    let count = "select count(*) from pg_prepared_statements where name = $1;";
+    assert_eq!(conn.query_scalar::<i64>(count, &[&name.as_str()])?, 1);
+
+    stmt.close(&conn)?;
+    assert_eq!(conn.query_scalar::<i64>(count, &[&name.as_str()])?, 0);
+
+    assert!(matches!(
+        conn.prepare_statement("selec 1;", &[]),
+        Err(PgError::Query { .. })
+    ));
+
+    Ok(())
+}
+
+/// ## Test: `exec_cached_statements`
+///
+/// Runs queries through the statement cache of `PgConn::exec_cached`.
+///
+/// ### Assertions
+///
+/// - Running the same SQL twice with different parameters prepares it only once, as
+///   reported by `pg_prepared_statements`, and returns the right rows both times.
+/// - Beyond the configured capacity, the oldest statement is deallocated.
+/// - `clear_statement_cache` deallocates every cached statement.
+#[test]
+fn exec_cached_statements() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let prepared = |conn: &PgConn, sql: &str| {
+        conn.query_scalar::<i64>(
+            "select count(*) from pg_prepared_statements where statement = $1;",
+            &[&sql],
+        )
+    };
+
+    let double = "select $1 * 2;";
+    assert_eq!(
+        conn.exec_cached(double, &[&21i64])?
+            .check()?
+            .get::<i64>(0, 0)?,
+        42
+    );
+    assert_eq!(
+        conn.exec_cached(double, &[&50i64])?
+            .check()?
+            .get::<i64>(0, 0)?,
+        100
+    );
+    assert_eq!(prepared(&conn, double)?, 1);
+
+    conn.set_statement_cache_capacity(2);
+    assert_eq!(conn.statement_cache_capacity(), 2);
+    conn.exec_cached("select 1;", &[])?.check()?;
+    conn.exec_cached("select 2;", &[])?.check()?;
+    assert_eq!(prepared(&conn, double)?, 0);
+    assert_eq!(prepared(&conn, "select 1;")?, 1);
+
+    conn.clear_statement_cache();
+    assert_eq!(
+        conn.query_scalar::<i64>("select count(*) from pg_prepared_statements;", &[])?,
+        0
+    );
+
+    Ok(())
+}
+
+/// ## Test: `with_transaction_closure`
+///
+/// Runs closures within `PgConn::with_transaction`, with an error type of the caller's own
+/// that `PgError` converts into.
+///
+/// ### Assertions
+///
+/// - When the closure returns `Ok`, its value is returned and its rows persist.
+/// - When it returns `Err`, that error is returned and its rows are rolled back.
+/// - When it panics, its rows are rolled back as well and the connection stays usable.
+#[test]
+fn with_transaction_closure() -> Result<(), PgError> {
+    #[derive(Debug)]
+    enum AppError {
+        Pg(PgError),
+        Rejected,
+    }
+
+    impl From<PgError> for AppError {
+        fn from(e: PgError) -> Self {
+            AppError::Pg(e)
+        }
+    }
+
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.execute("create temp table tx_closure (n int);", &[])?;
+
+    let inserted = conn.with_transaction(|tx| -> Result<u64, AppError> {
+        Ok(tx.execute("insert into tx_closure values (1), (2);", &[])?)
+    });
+    assert!(matches!(inserted, Ok(2)));
+
+    let rejected = conn.with_transaction(|tx| -> Result<(), AppError> {
+        tx.execute("insert into tx_closure values (3);", &[])?;
+        Err(AppError::Rejected)
+    });
+    assert!(matches!(rejected, Err(AppError::Rejected)));
+
+    let failed = conn.with_transaction(|tx| -> Result<(), AppError> {
+        tx.execute("insert into tx_closure values ('x');", &[])?;
+        Ok(())
+    });
+    assert!(matches!(failed, Err(AppError::Pg(PgError::Query { .. }))));
+
+    let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let _ = conn.with_transaction(|tx| -> Result<(), PgError> {
+            tx.execute("insert into tx_closure values (4);", &[])?;
+            panic!("closure panicked");
+        });
+    }));
+    assert!(panicked.is_err());
+
+    assert_eq!(
+        conn.query_scalar::<i64>("select count(*) from tx_closure;", &[])?,
+        2
+    );
+
+    Ok(())
+}
+
+/// ## Test: `conninfo_options`
+///
+/// Reads the options in effect on a connection with `PgConn::conninfo`.
+///
+/// ### Assertions
+///
+/// - The resolved `dbname` is reported and matches `PgConn::db`.
+/// - A password given in the connection string is reported blanked, its option being marked
+///   with dispchar `*`.
+#[test]
+fn conninfo_options() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let options = conn.conninfo();
+    let option = |options: &[ConnInfoOption], keyword: &str| {
+        options
+            .iter()
+            .find(|o| o.keyword == keyword)
+            .cloned()
+            .unwrap_or_else(|| panic!("No {} option.", keyword))
+    };
+    assert_eq!(option(&options, "dbname").value, conn.db());
+
+    let password = std::env::var("PGPASSWORD").unwrap_or_default();
+    let conn = PgConn::connect_db(&format!("password='{}'", password))
+        .expect("Failed to create PGconn from connection string.");
+    let option = option(&conn.conninfo(), "password");
+    assert_eq!(option.dispchar, "*");
+    assert_eq!(option.value.as_deref(), Some(""));
+}
+
+/// ## Test: `try_get_value_outcomes`
+///
+/// Checks the three outcomes of `PgResult::try_get_value`.
+///
+/// ### Assertions
+///
+/// - A NULL yields `Ok(None)`.
+/// - A value that is not a valid `i32` yields the `ParseIntError`.
+/// - A valid value yields `Ok(Some(5))`.
+#[test]
+fn try_get_value_outcomes() -> Result<(), PgError> {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn.exec("select null::int, 'abc', '5';")?.check()?;
+    assert_eq!(res.try_get_value::<i32>(0, 0), Ok(None));
+    assert!(matches!(
+        res.try_get_value::<i32>(0, 1),
+        Err(std::num::ParseIntError { .. })
+    ));
+    assert_eq!(res.try_get_value::<i32>(0, 2), Ok(Some(5)));
+
+    Ok(())
+}
+
+/// ## Test: `get_chrono_values`
+///
+/// Decodes `date`, `timestamp` and `timestamptz` values into `chrono` types.
+///
+/// ### Assertions
+///
+/// - A `date` and a `timestamp` with fractional seconds decode exactly.
+/// - A `timestamptz` decodes to the same instant whether the session time zone makes the
+///   server print it with a `+00` offset or a `+05:30` one.
+/// - Values that are not in the expected format, such as `infinity`, are an error.
+#[cfg(feature = "chrono")]
+#[test]
+fn get_chrono_values() -> Result<(), PgError> {
+    use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.exec("set datestyle to 'ISO, MDY'; set timezone to 'UTC';")?
+        .check()?;
+    let res = conn
+        .exec(
+            "select '2024-01-02'::date, '2024-01-02 03:04:05.678'::timestamp, \
+             '2024-01-02 03:04:05+00'::timestamptz, 'infinity'::date;",
+        )?
+        .check()?;
+    let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+    assert_eq!(res.get::<NaiveDate>(0, 0)?, date);
+    assert_eq!(
+        res.get::<NaiveDateTime>(0, 1)?,
+        date.and_hms_milli_opt(3, 4, 5, 678).unwrap()
+    );
+    let instant = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+    assert_eq!(res.get_value_raw(0, 2), "2024-01-02 03:04:05+00");
+    assert_eq!(res.get::<DateTime<Utc>>(0, 2)?, instant);
+    assert!(matches!(
+        res.get::<NaiveDate>(0, 3),
+        Err(PgError::InvalidValue { .. })
+    ));
+
+    conn.exec("set timezone to 'Asia/Kolkata';")?.check()?;
+    let res = conn
+        .exec("select '2024-01-02 03:04:05+00'::timestamptz;")?
+        .check()?;
+    assert_eq!(res.get_value_raw(0, 0), "2024-01-02 08:34:05+05:30");
+    assert_eq!(res.get::<DateTime<Utc>>(0, 0)?, instant);
+
+    Ok(())
+}
+
+/// ## Test: `uuid_round_trip`
+///
+/// Round-trips a `uuid::Uuid` generated by the server through a parameter and back.
+///
+/// ### Assertions
+///
+/// - A `gen_random_uuid()` value decodes into a `Uuid` whose canonical form is the text the
+///   server sent.
+/// - Bound as a parameter, it is inserted and matched as a `uuid`, and reads back equal.
+/// - Text that is not a UUID is reported as `PgError::InvalidValue`.
+#[cfg(feature = "uuid")]
+#[test]
+fn uuid_round_trip() -> Result<(), PgError> {
+    use uuid::Uuid;
+
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn.exec("select gen_random_uuid();")?.check()?;
+    let id = res.get::<Uuid>(0, 0)?;
+    assert_eq!(id.to_string(), res.get_value_raw(0, 0));
+
+    conn.execute("create temp table ids (id uuid primary key);", &[])?;
+    assert_eq!(conn.execute("insert into ids values ($1);", &[&id])?, 1);
+    assert_eq!(
+        conn.query_scalar::<Uuid>("select id from ids where id = $1;", &[&id])?,
+        id
+    );
+
+    let res = conn.exec("select 'not-a-uuid';")?.check()?;
+    assert!(matches!(
+        res.get::<Uuid>(0, 0),
+        Err(PgError::InvalidValue { value, .. }) if value == "not-a-uuid"
+    ));
+
+    Ok(())
+}
+
+/// ## Test: `decimal_round_trip`
+///
+/// Decodes `numeric` values into `rust_decimal::Decimal` and binds them back as parameters.
+///
+/// ### Assertions
+///
+/// - `12345.6789` decodes exactly, scale included, and so does a value with more
+///   significant digits than an `f64` holds.
+/// - Inserted back as a parameter and read again, the value is unchanged.
+/// - `NaN` is reported as `PgError::InvalidValue`.
+#[cfg(feature = "rust_decimal")]
+#[test]
+fn decimal_round_trip() -> Result<(), PgError> {
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec("select '12345.6789'::numeric, '1234567890.123456789012'::numeric, 'NaN'::numeric;")?
+        .check()?;
+    let amount = res.get::<Decimal>(0, 0)?;
+    assert_eq!(amount, Decimal::new(123456789, 4));
+    assert_eq!(amount.scale(), 4);
+    let precise = res.get::<Decimal>(0, 1)?;
+    assert_eq!(
+        precise,
+        Decimal::from_str("1234567890.123456789012").unwrap()
+    );
+    assert!(matches!(
+        res.get::<Decimal>(0, 2),
+        Err(PgError::InvalidValue { .. })
+    ));
+
+    conn.execute("create temp table amounts (a numeric);", &[])?;
+    conn.execute(
+        "insert into amounts values ($1), ($2);",
+        &[&amount, &precise],
+    )?;
+    let res = conn.exec("select a from amounts;")?.check()?;
+    assert_eq!(res.get::<Decimal>(0, 0)?, amount);
+    assert_eq!(res.get_value_raw(0, 0), "12345.6789");
+    assert_eq!(res.get::<Decimal>(1, 0)?, precise);
+
+    Ok(())
+}
+
+/// ## Test: `check_copy_in_progress`
+///
+/// Checks that `PgResult::check` reports a COPY that started as `PgError::CopyInProgress`,
+/// telling it apart from a failed command.
+///
+/// ### Assertions
+///
+/// - `COPY ... TO STDOUT` is reported as a copy out, and `COPY ... FROM STDIN` as a copy in.
+/// - A COPY that fails, on a missing table, is still a `PgError::Query`.
+/// - Data larger than libpq accepts in one call is rejected as invalid input, without
+///   breaking the copy.
+/// - Once the copies are finished, the connection is usable again.
+#[test]
+fn check_copy_in_progress() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.execute("create temp table t (n int);", &[])?;
+
+    let err = conn.exec("copy t to stdout;")?.check().unwrap_err();
+    assert!(matches!(err, PgError::CopyInProgress(s) if s == ExecStatusType_PGRES_COPY_OUT));
+    assert_eq!(err.to_string(), "COPY in progress: PGRES_COPY_OUT");
+    while conn.get_copy_data()?.is_some() {}
+    conn.get_result().expect("No COPY outcome.").check()?;
+    assert!(conn.get_result().is_none());
+
+    assert!(matches!(
+        conn.exec("copy t from stdin;")?.check(),
+        Err(PgError::CopyInProgress(s)) if s == ExecStatusType_PGRES_COPY_IN
+    ));
+    // Zeroed allocations are mapped lazily, so this doesn't actually take 2GB.
+    let huge = vec![0u8; i32::MAX as usize + 1];
+    assert!(matches!(
+        conn.put_copy_data(&huge),
+        Err(PgError::Io(e)) if e.kind() == std::io::ErrorKind::InvalidInput
+    ));
+    drop(huge);
+    conn.put_copy_data(b"1\n")?;
+    conn.put_copy_end(None)?;
+    assert_eq!(
+        conn.get_result()
+            .expect("No COPY outcome.")
+            .check()?
+            .cmd_tuples(),
+        1
+    );
+    assert!(conn.get_result().is_none());
+
+    assert!(matches!(
+        conn.exec("copy missing_table to stdout;")?.check(),
+        Err(PgError::Query { .. })
+    ));
+
+    assert_eq!(conn.query_scalar::<i32>("select 1;", &[])?, 1);
+
+    Ok(())
+}
+
+/// ## Test: `result_is_error_is_success`
+///
+/// Checks the `is_error`/`is_success` status predicates of `PgResult`.
+///
+/// ### Assertions
+///
+/// - A syntax error is an error and not a success.
+/// - `SELECT 1` and a command are successes and not errors.
+/// - An empty query is neither.
+#[test]
+fn result_is_error_is_success() -> Result<(), PgError> {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn.exec("selec 1;")?;
+    assert!(res.is_error());
+    assert!(!res.is_success());
+
+    for sql in ["select 1;", "set search_path to public;"] {
+        let res = conn.exec(sql)?;
+        assert!(res.is_success());
+        assert!(!res.is_error());
+    }
+
+    let res = conn.exec("")?;
+    assert!(!res.is_success());
+    assert!(!res.is_error());
+
+    Ok(())
+}
+
+/// ## Test: `ssl_object_pointer`
+///
+/// Checks that `PgConn::ssl_object` exposes the OpenSSL object of TLS connections only.
+///
+/// ### Assertions
+///
+/// - A connection with `sslmode=disable` has no SSL object.
+/// - A connection with `sslmode=require` has one, when the server accepts TLS at all (the
+///   check is skipped otherwise).
+#[test]
+fn ssl_object_pointer() {
+    let conn = PgConn::connect_db("sslmode=disable")
+        .expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+    assert!(conn.ssl_object().is_none());
+
+    let conn = PgConn::connect_db("sslmode=require")
+        .expect("Failed to create PGconn from connection string.");
+
+    if conn.status() == ConnStatusType_CONNECTION_OK {
+        assert!(conn.ssl_object().is_some_and(|ssl| !ssl.is_null()));
+    } else {
+        eprintln!("skipped: the server doesn't accept TLS connections");
+    }
+}
+
+/// ## Test: `connect_with_timeout_deadline`
+///
+/// Connects with `PgConn::connect_with_timeout`, which drives a non-blocking connection
+/// against a deadline.
+///
+/// ### Assertions
+///
+/// - Connecting to the server of the environment succeeds well within the timeout.
+/// - Connecting to a blackholed address fails, with `PgError::Timeout` when the packets are
+///   dropped or a connection error when the network is unreachable, within the timeout and
+///   not after the five seconds of `connect_timeout` given to libpq.
+/// - With no time at all, the deadline is checked between polls, failing with
+///   `PgError::Timeout` even though the server answers.
+#[test]
+fn connect_with_timeout_deadline() {
+    let conn = PgConn::connect_with_timeout("", Duration::from_secs(5))
+        .expect("Failed to connect within the timeout.");
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+    assert_eq!(conn.exec("select 1;").unwrap().get_value_raw(0, 0), "1");
+
+    let started = Instant::now();
+    let res = PgConn::connect_with_timeout(
+        "host=10.255.255.1 port=5432 connect_timeout=5",
+        Duration::from_millis(500),
+    );
+    assert!(matches!(
+        res,
+        Err(PgError::Timeout) | Err(PgError::Connection(_))
+    ));
+    assert!(started.elapsed() < Duration::from_secs(2));
+
+    assert!(matches!(
+        PgConn::connect_with_timeout("", Duration::ZERO),
+        Err(PgError::Timeout)
+    ));
+}
+
+/// ## Test: `pipeline_abort_until_sync`
+///
+/// Pipelines a good query, a failing one and another good one, then a sync point.
+///
+/// ### Assertions
+///
+/// - `pipeline_results()` returns one result per query followed by the sync result: the
+///   first query succeeds, the second fails and the third is reported as aborted.
+/// - The pipeline status is back to `PQ_PIPELINE_ON` once the sync result is read.
+/// - A query submitted after the sync point runs normally, and the connection leaves
+///   pipeline mode once all results are read.
+/// - Collecting results with no sync point pending fails instead of blocking.
+#[test]
+fn pipeline_abort_until_sync() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.enter_pipeline_mode()?;
+    assert_eq!(conn.pipeline_status(), PGpipelineStatus_PQ_PIPELINE_ON);
+
+    conn.send_query_params("SELECT 1", &[])?;
+    conn.send_query_params("SELECT 1 / 0", &[])?;
+    conn.send_query_params("SELECT 3", &[])?;
+    conn.pipeline_sync()?;
+
+    let results = conn.pipeline_results()?;
+    assert_eq!(results.len(), 4);
+    assert!(results[0].is_success());
+    assert_eq!(results[0].get_value_raw(0, 0), "1");
+    assert!(results[1].is_error());
+    assert_eq!(results[1].sqlstate().as_deref(), Some("22012"));
+    assert!(results[2].is_pipeline_aborted());
+    assert!(!results[2].is_error());
+    assert!(results[3].is_pipeline_sync());
+    assert_eq!(conn.pipeline_status(), PGpipelineStatus_PQ_PIPELINE_ON);
+
+    conn.send_query_params("SELECT 4", &[])?;
+    conn.pipeline_sync()?;
+    let results = conn.pipeline_results()?;
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].get_value_raw(0, 0), "4");
+    assert!(results[1].is_pipeline_sync());
+
+    assert!(matches!(
+        conn.pipeline_results(),
+        Err(PgError::Connection(_))
+    ));
+
+    conn.exit_pipeline_mode()?;
+    assert_eq!(conn.pipeline_status(), PGpipelineStatus_PQ_PIPELINE_OFF);
+
+    Ok(())
+}
+
+/// ## Test: `owned_rows_outlive_result`
+///
+/// Collects the values of a small result with `into_owned_rows`, then drops the result.
+///
+/// ### Assertions
+///
+/// - The rows collected before dropping the `PgResult` still hold its values, in order.
+/// - SQL NULL is `None`, distinct from an empty string.
+#[test]
+fn owned_rows_outlive_result() -> Result<(), PgError> {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec("SELECT * FROM (VALUES (1, 'one'), (2, NULL), (3, '')) AS t (n, name) ORDER BY n;")?
+        .check()?;
+    let rows = res.into_owned_rows();
+    drop(res);
+
+    assert_eq!(
+        rows,
+        vec![
+            vec![Some("1".to_string()), Some("one".to_string())],
+            vec![Some("2".to_string()), None],
+            vec![Some("3".to_string()), Some(String::new())],
+        ]
+    );
+
+    Ok(())
+}
+
+/// ## Test: `column_info`
+///
+/// Reads the metadata of all columns of a result at once with `PgResult::columns`.
+///
+/// ### Assertions
+///
+/// - `SELECT 1 AS a, 'x'::varchar(5) AS b` describes two columns, named `a` and `b`, of
+///   types `int4` and `varchar`, in text format.
+/// - The `varchar(5)` column has modifier `9` and a variable size, the `int4` one no
+///   modifier and size `4`; neither comes from a table.
+#[test]
+fn column_info() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec("SELECT 1 AS a, 'x'::varchar(5) AS b;")
+        .expect("Failed to execute query.");
+
+    let columns = res.columns();
+    assert_eq!(columns.len(), 2);
+    assert_eq!(
+        columns[0],
+        ColumnInfo {
+            name: "a".to_string(),
+            type_oid: INT4OID,
+            modifier: -1,
+            size: 4,
+            format: 0,
+            table_oid: INVALID_OID,
+            table_col: 0,
+        }
+    );
+    assert_eq!(columns[1].name, "b");
+    assert_eq!(columns[1].type_oid, VARCHAROID);
+    assert_eq!(columns[1].modifier, 9);
+    assert!(columns[1].size < 0);
+    assert_eq!(columns[1].format, 0);
+    assert_eq!(columns[1].table_oid, INVALID_OID);
+}
+
+/// ## Test: `row_as_map`
+///
+/// Reads rows as maps from column name to value with `PgResult::row_map`.
+///
+/// ### Assertions
+///
+/// - `SELECT 1 AS id, NULL AS note` maps `id` to `Some("1")` and `note` to `None`.
+/// - With two columns named `n`, the map holds the value of the last one.
+#[test]
+fn row_as_map() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec("SELECT 1 AS id, NULL AS note;")
+        .expect("Failed to execute query.");
+    let map = res.row_map(0);
+    assert_eq!(map.len(), 2);
+    assert_eq!(map["id"], Some("1".to_string()));
+    assert_eq!(map["note"], None);
+
+    let res = conn
+        .exec("SELECT 1 AS n, 2 AS n;")
+        .expect("Failed to execute query.");
+    let map = res.row_map(0);
+    assert_eq!(map.len(), 1);
+    assert_eq!(map["n"], Some("2".to_string()));
+}
+
+/// ## Test: `result_status_str`
+///
+/// Verifies the status names returned by `PgResult::status_str`.
+///
+/// ### Assertions
+///
+/// - A `SELECT 1` result is `"PGRES_TUPLES_OK"`.
+/// - A failed query's result is `"PGRES_FATAL_ERROR"`.
+#[test]
+fn result_status_str() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn.exec("SELECT 1;").expect("Failed to execute query.");
+    assert_eq!(res.status_str(), "PGRES_TUPLES_OK");
+
+    let res = conn
+        .exec("SELECT * FROM missing_table;")
+        .expect("Failed to execute query.");
+    assert_eq!(res.status_str(), "PGRES_FATAL_ERROR");
+}
+
+/// ## Test: `conn_status_str`
+///
+/// Verifies the connection status names returned by `PgConn::status_str` and
+/// `ConnStatus::as_str`.
+///
+/// ### Assertions
+///
+/// - A healthy connection is `"CONNECTION_OK"`, also when displayed.
+/// - `ConnStatus::Bad` is `"CONNECTION_BAD"`, and a value unknown to the crate is
+///   `"Unknown"`.
+#[test]
+fn conn_status_str() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    assert_eq!(conn.status_str(), "CONNECTION_OK");
+    assert_eq!(conn.status_enum().to_string(), "CONNECTION_OK");
+    assert_eq!(ConnStatus::Bad.as_str(), "CONNECTION_BAD");
+    assert_eq!(ConnStatus::from(1000).as_str(), "Unknown");
+}
+
+/// ## Test: `builder_service_and_passfile`
+///
+/// Connects through `PgConnBuilder::service`, with a temporary service file named by
+/// `PGSERVICEFILE`, and `PgConnBuilder::passfile`.
+///
+/// ### Assertions
+///
+/// - The parameters of the service, here its application name, are used for the
+///   connection.
+/// - The password file given to the builder is the one reported by `conninfo()`.
+/// - An undefined service makes the connection fail.
+#[test]
+fn builder_service_and_passfile() -> Result<(), PgError> {
+    let mut service_file = tempfile::NamedTempFile::new().expect("Failed to create temp file.");
+    service_file.write_all(b"[libpq_rs_test]\napplication_name=from service\n")?;
+    // Created readable by the owner only, as libpq requires of a password file.
+    let mut passfile = tempfile::NamedTempFile::new().expect("Failed to create temp file.");
+    passfile.write_all(b"*:*:*:*:unused\n")?;
+    let passfile = passfile.path().to_str().unwrap();
+    // SAFETY: no other test reads `PGSERVICEFILE`, and libpq of this version has no
+    // `servicefile` keyword to pass it as a parameter instead.
+    unsafe { std::env::set_var("PGSERVICEFILE", service_file.path()) };
+
+    let conn = PgConnBuilder::new()
+        .service("libpq_rs_test")
+        .passfile(passfile)
+        .connect()?;
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+    assert_eq!(
+        conn.parameter_status("application_name")?.as_deref(),
+        Some("from service")
+    );
+    let option = conn
+        .conninfo()
+        .into_iter()
+        .find(|option| option.keyword == "passfile")
+        .unwrap();
+    assert_eq!(option.value.as_deref(), Some(passfile));
+
+    assert!(matches!(
+        PgConnBuilder::new().service("no_such_service").connect(),
+        Err(PgError::Connection(_))
+    ));
+
+    Ok(())
+}
+
+/// ## Test: `builder_keepalives`
+///
+/// Connects through `PgConnBuilder` with TCP keepalives configured.
+///
+/// ### Assertions
+///
+/// - The connection succeeds.
+/// - `conninfo()` reports each keepalive option with the value given to the builder, the
+///   durations in seconds.
+/// - Sub-second durations are rounded up to one second rather than down to zero, which
+///   would mean the system default.
+#[test]
+fn builder_keepalives() -> Result<(), PgError> {
+    let conn = PgConnBuilder::new()
+        .keepalives(true)
+        .keepalives_idle(Duration::from_secs(60))
+        .keepalives_interval(Duration::from_secs(10))
+        .keepalives_count(5)
+        .connect()?;
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let options = conn.conninfo();
+    let value = |keyword: &str| {
+        options
+            .iter()
+            .find(|option| option.keyword == keyword)
+            .and_then(|option| option.value.clone())
+    };
+    assert_eq!(value("keepalives").as_deref(), Some("1"));
+    assert_eq!(value("keepalives_idle").as_deref(), Some("60"));
+    assert_eq!(value("keepalives_interval").as_deref(), Some("10"));
+    assert_eq!(value("keepalives_count").as_deref(), Some("5"));
+
+    let conn = PgConnBuilder::new()
+        .keepalives_idle(Duration::from_millis(500))
+        .keepalives_interval(Duration::from_millis(1500))
+        .connect()?;
+    let options = conn.conninfo();
+    let value = |keyword: &str| {
+        options
+            .iter()
+            .find(|option| option.keyword == keyword)
+            .and_then(|option| option.value.clone())
+    };
+    assert_eq!(value("keepalives_idle").as_deref(), Some("1"));
+    assert_eq!(value("keepalives_interval").as_deref(), Some("2"));
+
+    Ok(())
+}
+
+/// ## Test: `connection_error_verbosity`
+///
+/// Sets the verbosity and context visibility of a connection's error messages with the
+/// `Verbosity` and `ContextVisibility` enums.
+///
+/// ### Assertions
+///
+/// - `set_error_verbosity` returns the previous setting, `Verbosity::Default` at first.
+/// - With `Verbosity::Terse`, the error of a query on a missing table is a single line,
+///   where by default it also points at the position in the query.
+/// - With `Verbosity::Sqlstate`, the message only has the severity and SQLSTATE.
+/// - `set_error_context_visibility` returns the previous setting,
+///   `ContextVisibility::Errors` at first.
+#[test]
+fn connection_error_verbosity() {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.exec("SELECT * FROM missing_table;")
+        .expect("Failed to execute query.");
+    assert!(conn.error_message().lines().count() > 1);
+
+    assert_eq!(
+        conn.set_error_verbosity(Verbosity::Terse),
+        Verbosity::Default
+    );
+    conn.exec("SELECT * FROM missing_table;")
+        .expect("Failed to execute query.");
+    assert_eq!(conn.error_message().lines().count(), 1);
+
+    assert_eq!(
+        conn.set_error_verbosity(Verbosity::Sqlstate),
+        Verbosity::Terse
+    );
+    conn.exec("SELECT * FROM missing_table;")
+        .expect("Failed to execute query.");
+    assert_eq!(conn.error_message(), "ERROR:  42P01\n");
+
+    assert_eq!(
+        conn.set_error_context_visibility(ContextVisibility::Always),
+        ContextVisibility::Errors
+    );
+    assert_eq!(
+        conn.set_error_context_visibility(ContextVisibility::Errors),
+        ContextVisibility::Always
+    );
+}
+
+/// ## Test: `notice_receiver_info`
+///
+/// Captures a `NOTICE` and a `WARNING` through a notice receiver, reading their fields with
+/// `PgResult::notice`.
+///
+/// ### Assertions
+///
+/// - Each notice arrives with its severity and primary message, in order.
+/// - The detail and hint of the warning are kept, and are `None` for the plain notice.
+#[test]
+fn notice_receiver_info() {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let (tx, rx) = mpsc::channel();
+    conn.set_notice_receiver(move |r| tx.send(r.notice()).unwrap());
+
+    conn.exec(
+        "do $$ begin
+            raise notice 'just saying';
+            raise warning 'careful' using detail = 'some detail', hint = 'some hint';
+        end $$;",
+    )
+    .expect("Failed to execute query.");
+
+    assert_eq!(
+        rx.try_recv(),
+        Ok(NoticeInfo {
+            severity: "NOTICE".to_string(),
+            message: "just saying".to_string(),
+            detail: None,
+            hint: None,
+        })
+    );
+    assert_eq!(
+        rx.try_recv(),
+        Ok(NoticeInfo {
+            severity: "WARNING".to_string(),
+            message: "careful".to_string(),
+            detail: Some("some detail".to_string()),
+            hint: Some("some hint".to_string()),
+        })
+    );
+    assert_eq!(rx.try_recv(), Err(mpsc::TryRecvError::Empty));
+}
+
+/// ## Test: `log_notices_levels`
+///
+/// Forwards notices to the `log` crate with `PgConn::log_notices`, capturing them with a
+/// test logger.
+///
+/// ### Assertions
+///
+/// - A `RAISE WARNING` is logged at `Level::Warn` and a `RAISE NOTICE` at `Level::Info`,
+///   with target `libpq` and without the severity prefix.
+#[cfg(feature = "log")]
+#[test]
+fn log_notices_levels() {
+    struct TestLogger(Mutex<Vec<(log::Level, String)>>);
+
+    impl log::Log for TestLogger {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.target() == "libpq"
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                let entry = (record.level(), record.args().to_string());
+                self.0.lock().unwrap().push(entry);
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: TestLogger = TestLogger(Mutex::new(Vec::new()));
+    log::set_logger(&LOGGER).expect("Failed to install the test logger.");
+    log::set_max_level(log::LevelFilter::Trace);
+
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.log_notices();
+    conn.exec("do $$ begin raise warning 'disk almost full'; raise notice 'all good'; end $$;")
+        .expect("Failed to execute query.");
+
+    assert_eq!(
+        *LOGGER.0.lock().unwrap(),
+        vec![
+            (log::Level::Warn, "disk almost full".to_string()),
+            (log::Level::Info, "all good".to_string()),
+        ]
+    );
+}
+
+/// ## Test: `print_errors`
+///
+/// Verifies that `PgResult::print` reports failures instead of panicking.
+///
+/// ### Assertions
+///
+/// - Printing to a file in a missing directory fails with `PgError::Io`, of kind `NotFound`.
+/// - A field separator or file name containing a NUL byte fails with `PgError::Nul`.
+#[test]
+fn print_errors() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn.exec("select 1;").expect("Failed to execute query.");
+    let print = |filename: &str, fieldsep: &str| {
+        res.print(filename, true, true, fieldsep, true, false, false, false)
+    };
+
+    assert!(matches!(
+        print("./test-out/missing-dir/res.out", "|"),
+        Err(PgError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound
+    ));
+    assert!(matches!(
+        print("./test-out/res.out", "|\0"),
+        Err(PgError::Nul(_))
+    ));
+    assert!(matches!(
+        print("./test-out/res\0.out", "|"),
+        Err(PgError::Nul(_))
+    ));
+}
+
+/// ## Test: `send_describe`
+///
+/// Describes a prepared statement and a cursor asynchronously, with
+/// `PgConn::send_describe_prepared` and `PgConn::send_describe_portal`.
+///
+/// ### Assertions
+///
+/// - The result read with `get_result` for the statement has its two parameters, of the
+///   declared types, and its result column.
+/// - The result for the cursor has its two columns, by name, and no rows.
+/// - Describing a missing statement yields a failed result.
+#[test]
+fn send_describe() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.prepare("add", "select $1 + $2 as sum;", &[INT4OID, INT8OID])?
+        .check()?;
+
+    conn.send_describe_prepared("add")?;
+    let res = conn.get_result().unwrap().check()?;
+    assert!(conn.get_result().is_none());
+    assert_eq!(res.nparams(), 2);
+    assert_eq!(res.param_type(0), INT4OID);
+    assert_eq!(res.param_type(1), INT8OID);
+    assert_eq!(res.nfields(), 1);
+    assert_eq!(res.field_name(0).as_deref(), Some("sum"));
+
+    conn.exec("begin; declare c cursor for select 1 as one, 'x' as two;")?
+        .check()?;
+    conn.send_describe_portal("c")?;
+    let res = conn.get_result().unwrap().check()?;
+    assert!(conn.get_result().is_none());
+    assert_eq!(res.nfields(), 2);
+    assert_eq!(res.field_name(1).as_deref(), Some("two"));
+    assert_eq!(res.ntuples(), 0);
+    conn.exec("rollback;")?.check()?;
+
+    conn.send_describe_prepared("missing")?;
+    let res = conn.get_result().unwrap();
+    assert!(res.is_error());
+    assert!(conn.get_result().is_none());
+
+    Ok(())
+}
+
+/// ## Test: `libpq_buffers_freed`
+///
+/// Repeatedly calls the wrappers returning buffers allocated by libpq, which must free
+/// each one. Leaks are reported when run under LeakSanitizer, see `make test-asan`.
+///
+/// ### Assertions
+///
+/// - `escape_identifier` and `verbose_error_message` keep returning the same values over
+///   many calls.
+#[test]
+fn libpq_buffers_freed() {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let res = conn
+        .exec("select * from missing_table;")
+        .expect("Failed to execute query.");
+    let expected = res.verbose_error_message(Verbosity::Terse, ContextVisibility::Never);
+
+    for _ in 0..10_000 {
+        assert_eq!(
+            conn.escape_identifier("Some \"name\"").unwrap(),
+            "\"Some \"\"name\"\"\""
+        );
+        assert_eq!(
+            res.verbose_error_message(Verbosity::Terse, ContextVisibility::Never),
+            expected
+        );
+    }
+}
+
+/// ## Test: `trace_append_mode`
+///
+/// Traces two queries in turn with `PgConn::trace_append`, then one with `PgConn::trace`.
+///
+/// ### Assertions
+///
+/// - After the two appending traces, the file holds the `Query` messages of both queries,
+///   in order.
+/// - `trace` truncates the file, leaving only the last query.
+/// - A path with a nul byte, or in a missing directory, is reported as an error.
+#[test]
+fn trace_append_mode() {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let file = tempfile::NamedTempFile::new().expect("Failed to create temp file.");
+    let path = file.path().to_str().unwrap();
+
+    for query in ["select 4242;", "select 4343;"] {
+        conn.trace_append(path)
+            .expect("Failed to open the trace file.");
+        conn.exec(query).expect("Failed to execute query.");
+        conn.untrace();
+    }
+
+    let log = fs::read_to_string(path).unwrap();
+    let first = log.find("Query\t \"select 4242;\"").unwrap();
+    let second = log.find("Query\t \"select 4343;\"").unwrap();
+    assert!(first < second);
+
+    conn.trace(path).expect("Failed to open the trace file.");
+    conn.exec("select 4444;").expect("Failed to execute query.");
+    conn.untrace();
+
+    let log = fs::read_to_string(path).unwrap();
+    assert!(!log.contains("select 4242;"));
+    assert!(log.contains("Query\t \"select 4444;\""));
+
+    assert!(matches!(
+        conn.trace_append("trace\0.log"),
+        Err(PgError::Nul(_))
+    ));
+    let missing = file.path().join("trace.log");
+    assert!(matches!(
+        conn.trace(missing.to_str().unwrap()),
+        Err(PgError::Io(_))
+    ));
+}
+
+/// ## Test: `password_encryption_algorithm`
+///
+/// Reads the server's password hashing algorithm with `PgConn::password_encryption` and
+/// hashes passwords with `PgConn::encrypt_password`.
+///
+/// ### Assertions
+///
+/// - The algorithm is `scram-sha-256` or `md5`, the ones a modern server supports.
+/// - By default the password is hashed with that algorithm, e.g. as `SCRAM-SHA-256$...`.
+/// - An explicit `md5` gives `md5` followed by 32 hex digits.
+/// - An unknown algorithm is rejected with `PgError::Connection`.
+/// - On a failed connection, reading the algorithm reports an error.
+#[test]
+fn password_encryption_algorithm() -> Result<(), PgError> {
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let algorithm = conn.password_encryption()?;
+    assert!(["scram-sha-256", "md5"].contains(&algorithm.as_str()));
+
+    let encrypted = conn.encrypt_password("secret", "someone", None)?;
+    let prefix = if algorithm == "md5" {
+        "md5"
+    } else {
+        "SCRAM-SHA-256$"
+    };
+    assert!(encrypted.starts_with(prefix));
+
+    let md5 = conn.encrypt_password("secret", "someone", Some("md5"))?;
+    assert_eq!(md5.len(), 35);
+    assert!(md5.starts_with("md5"));
+    assert!(md5[3..].chars().all(|c| c.is_ascii_hexdigit()));
+
+    assert!(matches!(
+        conn.encrypt_password("secret", "someone", Some("rot13")),
+        Err(PgError::Connection(_))
+    ));
+
+    let bad =
+        PgConn::connect_db("port=1").expect("Failed to create PGconn from connection string.");
+    assert!(bad.password_encryption().is_err());
+
+    Ok(())
+}
+
+/// ## Test: `send_query_results`
+///
+/// Collects the results of a multi-statement `send_query` with `PgConn::results`.
+///
+/// ### Assertions
+///
+/// - `SELECT 1; SELECT 2` yields two results, in order.
+/// - The iterator ends once all results are read, and is empty with nothing in progress.
+/// - A statement starting a COPY ends the iteration with its `COPY_IN` result, even
+///   though libpq keeps returning one; once the copy is done, the results of the
+///   following statements are read.
+#[test]
+fn send_query_results() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.send_query("SELECT 1; SELECT 2")?;
+    let results: Vec<PgResult> = conn.results().collect();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].get_value_raw(0, 0), "1");
+    assert_eq!(results[1].get_value_raw(0, 0), "2");
+
+    assert_eq!(conn.results().count(), 0);
+
+    conn.exec("create temp table cp (n int);")?.check()?;
+    conn.send_query("SELECT 1; COPY cp FROM STDIN; SELECT 2")?;
+    let statuses: Vec<_> = conn.results().take(10).map(|res| res.status()).collect();
+    assert_eq!(
+        statuses,
+        [ExecStatusType_PGRES_TUPLES_OK, ExecStatusType_PGRES_COPY_IN]
+    );
+    conn.put_copy_data(b"1\n")?;
+    conn.put_copy_end(None)?;
+    let results: Vec<PgResult> = conn.results().collect();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].cmd_status(), "COPY 1");
+    assert_eq!(results[1].get_value_raw(0, 0), "2");
+
+    Ok(())
+}
+
+/// ## Test: `copy_escape_fields`
+///
+/// Escapes values for COPY text format with `copy_escape_field`, then loads them.
+///
+/// ### Assertions
+///
+/// - A value with a tab, a newline, a carriage return and a backslash has each of them
+///   escaped, and `None` is `\N`.
+/// - A row built from escaped fields round-trips through `copy_in_reader` unchanged.
+#[test]
+fn copy_escape_fields() -> Result<(), PgError> {
+    let value = "a\tb\nc\rd\\e";
+    assert_eq!(copy_escape_field(Some(value)), "a\\tb\\nc\\rd\\\\e");
+    assert_eq!(copy_escape_field(Some("")), "");
+    assert_eq!(copy_escape_field(None), "\\N");
+
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.exec("create temp table t (a text, b text);")?
+        .check()?;
+    let row = format!(
+        "{}\t{}\n",
+        copy_escape_field(Some(value)),
+        copy_escape_field(None)
+    );
+    assert_eq!(conn.copy_in_reader("t", &mut row.as_bytes())?, 1);
+
+    let res = conn.exec("select a, b from t;")?.check()?;
+    assert_eq!(res.get_value_raw(0, 0), value);
+    assert!(res.is_null(0, 1));
+
+    Ok(())
+}
+
+/// ## Test: `collect_notifications_payloads`
+///
+/// Collects notifications sent from another connection with
+/// `PgConn::collect_notifications`.
+///
+/// ### Assertions
+///
+/// - Three notifications with distinct payloads are returned in order, each with the
+///   channel name, its payload and the sender's backend PID.
+/// - A notification on another channel listened on is skipped.
+/// - With nothing more sent, waiting for one more returns none once the timeout elapses.
+#[test]
+fn collect_notifications_payloads() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.listen("collect_other")?.check()?;
+
+    let sender = thread::spawn(|| {
+        thread::sleep(Duration::from_millis(100));
+        let mut conn =
+            PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+        conn.notify("collect_other", Some("skipped")).unwrap();
+        for payload in ["first", "second", "third"] {
+            conn.notify("collected", Some(payload)).unwrap();
+        }
+        conn.query_scalar::<i32>("select pg_backend_pid();", &[])
+            .unwrap()
+    });
+
+    let notifications = conn.collect_notifications("collected", 3, Some(Duration::from_secs(5)))?;
+    let pid = sender.join().expect("Sender thread panicked.");
+    assert_eq!(
+        notifications,
+        ["first", "second", "third"]
+            .map(|payload| ("collected".to_string(), payload.to_string(), pid))
+            .to_vec()
+    );
+
+    let started = Instant::now();
+    assert!(
+        conn.collect_notifications("collected", 1, Some(Duration::from_millis(200)))?
+            .is_empty()
+    );
+    assert!(started.elapsed() >= Duration::from_millis(150));
+
+    Ok(())
+}
+
+/// ## Test: `result_instance_data`
+///
+/// Attaches values to results with `PgResult::set_instance_data`.
+///
+/// ### Assertions
+///
+/// - Results created before `PgConn::enable_instance_data` cannot hold instance data.
+/// - Enabling it twice is harmless.
+/// - A new result has no instance data; once set, it is read back, setting it again
+///   replaces it, and `0` removes it.
+/// - Results of a pipeline can each be tagged with the index of their query.
+/// - Results not created by a connection cannot hold instance data.
+#[test]
+fn result_instance_data() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let mut before = conn.exec("select 1;")?;
+    assert!(!before.set_instance_data(1));
+    conn.enable_instance_data()?;
+    conn.enable_instance_data()?;
+    assert!(!before.set_instance_data(1));
+
+    let mut res = conn.exec("select 1;")?;
+    assert_eq!(res.instance_data(), None);
+    assert!(res.set_instance_data(42));
+    assert_eq!(res.instance_data(), Some(42));
+    assert!(res.set_instance_data(usize::MAX));
+    assert_eq!(res.instance_data(), Some(usize::MAX));
+    assert!(res.set_instance_data(0));
+    assert_eq!(res.instance_data(), None);
+
+    conn.enter_pipeline_mode()?;
+    conn.send_query_params("select 1;", &[])?;
+    conn.send_query_params("select 2;", &[])?;
+    conn.pipeline_sync()?;
+    let mut results = conn.pipeline_results()?;
+    for (i, res) in results.iter_mut().enumerate() {
+        assert!(res.set_instance_data(i + 1));
+    }
+    let tags: Vec<_> = results.iter().map(|res| res.instance_data()).collect();
+    assert_eq!(tags, [Some(1), Some(2), Some(3)]);
+    conn.exit_pipeline_mode()?;
+
+    let mut empty = PgResult::make_empty(ExecStatusType_PGRES_COMMAND_OK);
+    assert!(!empty.set_instance_data(1));
+    assert_eq!(empty.instance_data(), None);
+
+    Ok(())
+}
+
+/// ## Test: `close_releases_session`
+///
+/// Closes a connection with `PgConn::close` while another one watches its server session.
+///
+/// ### Assertions
+///
+/// - The session of the closed connection disappears from `pg_stat_activity`, without
+///   waiting for the value to go out of scope.
+#[test]
+fn close_releases_session() -> Result<(), PgError> {
+    let observer =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+    let conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    let pid: i32 = conn.query_scalar("select pg_backend_pid();", &[])?;
+    let sessions = || {
+        observer.query_scalar::<i64>(
+            "select count(*) from pg_stat_activity where pid = $1;",
+            &[&pid],
+        )
+    };
+    assert_eq!(sessions()?, 1);
+
+    conn.close();
+
+    // The backend exits shortly after receiving the termination message.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while sessions()? > 0 && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(sessions()?, 0);
+
+    Ok(())
+}
+
+/// ## Test: `wait_ready_slow_query`
+///
+/// Waits for the result of a slow query submitted with `send_query` via `PgConn::wait_ready`.
+///
+/// ### Assertions
+///
+/// - With a timeout much shorter than the query, `wait_ready` returns `false` and the
+///   connection is still busy.
+/// - Without a timeout, it returns `true` once the query is done, after which
+///   `get_result` yields its result without blocking.
+/// - With nothing pending, it returns `true` right away.
+#[test]
+fn wait_ready_slow_query() -> Result<(), PgError> {
+    let mut conn =
+        PgConn::connect_db_env_vars().expect("Failed to create PGconn from connection string.");
+
+    assert_eq!(conn.status(), ConnStatusType_CONNECTION_OK);
+
+    conn.send_query("select pg_sleep(0.5), 42;")?;
+    assert!(!conn.wait_ready(Some(Duration::from_millis(50)))?);
+    assert!(conn.is_busy());
+
+    assert!(conn.wait_ready(None)?);
+    assert!(!conn.is_busy());
+    let res = conn.get_result().expect("Missing query result.").check()?;
+    assert_eq!(res.get_value(0, 1), Some("42".to_string()));
+    assert!(conn.get_result().is_none());
+
+    assert!(conn.wait_ready(Some(Duration::ZERO))?);
+
+    Ok(())
+}